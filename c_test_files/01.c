@@ -1,3 +1,4 @@
+// exit: 100
 int main()
 {
     return 100;
@@ -1,3 +1,4 @@
+// exit: 124
 int main()
 {
     int a = 124;
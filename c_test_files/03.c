@@ -1,3 +1,4 @@
+// exit: 9
 int some_func(int a)
 {
     return a;
@@ -1,3 +1,4 @@
+// exit: 4
 int add_two(int a)
 {
     int b = a + 100;
@@ -1,12 +1,13 @@
+// exit: 0
 int main()
 {
-    int* size = 4;
+    long size = 1;
     int a = 1;
     int b = 2;
     int c = 3;
     int d = 4;
 
-    // Do something really stupid
+    // Do something really stupid, stepping back one int at a time from a to reach d
     int* a_ptr = &a;
     int* b_ptr = a_ptr - size;
     int* c_ptr = b_ptr - size;
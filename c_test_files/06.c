@@ -1,12 +1,19 @@
+// exit: 0
+// stdout: Hello World! has 12 characters
+// stdout: Hello Universe!
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
 int main()
 {
     char* hello_world = "Hello World!";
-    int* string_length = alloc(4);
-    *string_length = 13;
+    size_t* string_length = malloc(sizeof(size_t));
+    *string_length = strlen(hello_world);
 
-    println(hello_world, *string_length);
+    printf("%s has %d characters\n", hello_world, *string_length);
 
-    println("Hello Universe!", 16);
+    puts("Hello Universe!");
 
     return 0;
 }
@@ -1,32 +1,36 @@
+// exit: 32
+// stdout: a is equal to 0
+// stdout: a is less than or equal to 0
+// stdout: a is greater than or equal to 0
+#include <stdio.h>
+
 int main()
 {
     int a = 0;
 
-    // println(char* str, int len)
-
     if (a > 0)
     {
-        println("a is greater than 0", 20);
+        puts("a is greater than 0");
     }
 
     if (a == 0)
     {
-        println("a is equal to 0", 16);
+        puts("a is equal to 0");
     }
 
     if (a <= 0)
     {
-        println("a is less than or equal to 0", 29);
+        puts("a is less than or equal to 0");
     }
 
     if (a >= 0)
     {
-        println("a is greater than or equal to 0", 32);
+        puts("a is greater than or equal to 0");
     }
 
     if (a < 0)
     {
-        println("a is less than 0", 17);
+        puts("a is less than 0");
     }
 
     return;
@@ -0,0 +1,23 @@
+// exit: 100
+int add(int a, int b)
+{
+    return a + b;
+}
+
+int apply(int (*op)(int, int), int a, int b)
+{
+    return op(a, b);
+}
+
+int main()
+{
+    int (*fp)(int, int) = add;
+    int (*other)(int, int) = &add;
+    int* pointers[4];
+    int (*ptr_to_array)[4];
+
+    int a = (*fp)(40, 2);
+    int b = apply(other, a, 58);
+
+    return b;
+}
@@ -0,0 +1,25 @@
+// exit: 0
+int main()
+{
+    unsigned char byte = 250;
+    short small = -2;
+    long big = small;
+    unsigned long long huge = 4294967296;
+    unsigned int divisor = 2;
+
+    // -2 converted to unsigned is a very large number, so this is false
+    if (small < divisor)
+    {
+        return 1;
+    }
+
+    // big was sign extended from -2
+    if (big == -2)
+    {
+        unsigned int half = byte / divisor;
+        long remainder = huge % 7;
+        return half - 125 + remainder * 0;
+    }
+
+    return 2;
+}
@@ -0,0 +1,25 @@
+// exit: 52
+double average(double a, double b)
+{
+    return (a + b) / 2;
+}
+
+float scale(float value, int factor)
+{
+    return value * factor;
+}
+
+int main()
+{
+    double a = 1.5;
+    float b = scale(2.25, 4);
+    double mean = average(a, b);
+
+    if (mean > 6.0)
+    {
+        return 0;
+    }
+
+    int truncated = mean * 10;
+    return truncated;
+}
@@ -0,0 +1,27 @@
+// exit: 20
+int is_digit(char c)
+{
+    return c >= '0' && c <= '9';
+}
+
+int main()
+{
+    int a = 3;
+    int b = 0;
+    _Bool flag = a;
+    bool none = false;
+
+    // b is never divided by as the left side is false
+    if (b != 0 && a / b > 1)
+    {
+        return 1;
+    }
+
+    if (!none && (flag || a / b))
+    {
+        int digits = is_digit('7') + is_digit('x') + (a > b);
+        return digits * 10 + !a;
+    }
+
+    return 2;
+}
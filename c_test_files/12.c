@@ -0,0 +1,15 @@
+// exit: 31
+int main()
+{
+    int flags = 5 | 8;
+    int mask = ~0 << 2;
+    unsigned int high = 2147483648;
+    int negative = -16;
+    int* ptr = &flags;
+
+    // & binds looser than ==, so this is flags & (1 == 1)
+    int low_bit = flags & 1 == 1;
+    int shifted = (negative >> 2) + (high >> 28);
+
+    return (flags ^ 3) + (mask & 12) + low_bit + shifted + (*ptr & 0);
+}
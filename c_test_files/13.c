@@ -0,0 +1,42 @@
+// exit: 31
+// stdout: Hello
+#include <stdio.h>
+
+void copy(char* to, char* from, int count)
+{
+    // Each pointer is only evaluated once per assignment
+    if (count > 0)
+    {
+        *to++ = *from++;
+        copy(to, from, count - 1);
+    }
+    return;
+}
+
+int main()
+{
+    int total = 10;
+    total += 5;
+    total *= 2;
+    total -= 4;
+    total /= 2;
+    total %= 7;
+    total <<= 3;
+    total >>= 1;
+    total |= 1;
+    total &= 13;
+    total ^= 2;
+
+    int i = 0;
+    i++;
+    ++i;
+    int before = i--;
+    int after = --i;
+
+    char* message = "Hello";
+    char buffer[6];
+    copy(buffer, message, 6);
+    puts(buffer);
+
+    return total + before * 10 + after;
+}
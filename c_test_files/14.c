@@ -0,0 +1,25 @@
+// exit: 31
+int next_value(int* counter)
+{
+    *counter -= 1;
+    return *counter;
+}
+
+int main()
+{
+    int a;
+    int b;
+    int c = 0;
+    int counter = 3;
+
+    a = b = 7;
+    c += a = 2;
+
+    int value;
+    if ((value = next_value(&counter)) != 0)
+    {
+        c = c * 10 + value;
+    }
+
+    return a + b + c;
+}
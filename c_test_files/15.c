@@ -0,0 +1,26 @@
+// exit: 44
+int max(int a, int b)
+{
+    return a > b ? a : b;
+}
+
+int count(int* counter)
+{
+    *counter += 1;
+    return *counter;
+}
+
+int main()
+{
+    int calls = 0;
+    int* counter = &calls;
+
+    // Only the chosen arm is evaluated, so count is called once
+    int a = 1 ? count(counter) : count(counter);
+    int b = (count(counter), count(counter), 40);
+
+    int largest = max((a, b), calls);
+    int sign = largest < 0 ? -1 : largest == 0 ? 0 : 1;
+
+    return largest + sign + calls;
+}
@@ -0,0 +1,21 @@
+// exit: 81
+#include <stdlib.h>
+
+int main()
+{
+    int values[4];
+    char* names[3];
+    long (*table)[2];
+    double ratio = 0.5;
+
+    int* buffer = malloc(sizeof(int) * 4);
+
+    unsigned long total = sizeof values + sizeof(names) + sizeof *table + sizeof ratio;
+    unsigned long alignment = _Alignof(double) + _Alignof(char[3]);
+
+    // sizeof does not evaluate its operand, so total is not incremented here
+    unsigned long size = sizeof(total++);
+
+    // 16 + 24 + 16 + 8 + 8 + 1 + 8
+    return total + alignment + size;
+}
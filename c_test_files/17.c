@@ -0,0 +1,23 @@
+// exit: 47
+int main()
+{
+    int big = 300;
+    char truncated = (char)big;
+    unsigned char byte = (unsigned char)-1;
+    long widened = (long)(int)byte;
+    long sign_extended = (long)(signed char)byte;
+
+    int value = 42;
+    int* ptr = &value;
+    unsigned long address = (unsigned long)ptr;
+    int* back = (int*)address;
+    char* bytes = (char*)back;
+
+    double ratio = (double)value / 8;
+    int whole = (int)ratio;
+
+    (void)bytes;
+
+    // 44 + 255 - 1 + 5
+    return truncated + widened + sign_extended + whole + (*back - value);
+}
@@ -0,0 +1,31 @@
+// exit: 18
+extern int shared_total;
+int counter = 5;
+long uninitialised;
+double scale = 2;
+char* greeting = "Hello";
+int (*handler)(int) = 0;
+
+static int helper(int value)
+{
+    return value * 2;
+}
+
+int next_id()
+{
+    // Keeps its value between calls
+    static int id = 0;
+    id += 1;
+    return id;
+}
+
+int main()
+{
+    next_id();
+    next_id();
+
+    uninitialised = counter;
+    counter = helper(counter);
+
+    return next_id() + counter + uninitialised;
+}
@@ -0,0 +1,32 @@
+// exit: 14
+const int limit = 10;
+const char* const message = "Hello";
+volatile int ticks = 0;
+
+int length(const char* text)
+{
+    int count = 0;
+    const char* end = text;
+    if (*end != 0) { count += 1; }
+    return count;
+}
+
+int main()
+{
+    int value = 3;
+    int* const fixed = &value;
+    const int* view = &value;
+    const volatile int status = 1;
+
+    // The pointer is constant but what it points to isn't
+    *fixed = limit;
+
+    ticks++;
+    ticks++;
+
+    // Reading through a pointer to const is fine, writing through it isn't
+    // *view = 4;
+    // limit = 2;
+
+    return *view + ticks + status + length("Hi");
+}
@@ -0,0 +1,21 @@
+// exit: 119
+char* first = "Hello, world";
+char* second = "Hello, world";
+
+// Shares its storage with the end of "Hello, world"
+char* tail = "world";
+
+int main()
+{
+    char* joined = "Hello, " "world";
+    char* quoted = "She said \"hi\"\n";
+
+    // Identical literals are interned, so all three point to the same bytes
+    if (first == second) {
+        if (first == joined) {
+            return *tail;
+        }
+    }
+
+    return *quoted;
+}
@@ -0,0 +1,19 @@
+// Sizes and initialisers only need to be integer constant expressions
+// exit: 53
+int table[2 * 4 + 1];
+long mask = ((long)1 << 40) - 1;
+unsigned int wrapped = (unsigned int)0 - 1;
+int negative = -7 / 2;
+double half = 1 / 2;
+
+int main()
+{
+    char buffer[sizeof(long) * 2];
+
+    // Folded to a single constant, (unsigned char)300 is 44
+    int small = (unsigned char)300;
+    int shifted = -16 >> 2;
+    int is_less = (unsigned int)-1 < 1;
+
+    return small + shifted + is_less + sizeof(buffer) + negative;
+}
@@ -0,0 +1,31 @@
+// Build with --libc, the bundled printf has no %f
+// args: --libc
+// exit: 6
+// stdout: 42 0.500000 done
+#include <stdio.h>
+#include <stdarg.h>
+
+long sum(int count, ...)
+{
+    va_list arguments;
+    va_start(arguments, count);
+
+    long total = 0;
+    // No loops yet, so this only ever reads the first three
+    if (count > 0) { total += va_arg(arguments, int); }
+    if (count > 1) { total += va_arg(arguments, long); }
+    if (count > 2) { total += va_arg(arguments, double); }
+
+    va_end(arguments);
+    return total;
+}
+
+int main()
+{
+    float ratio = 0.5;
+
+    // ratio is promoted to double and al is set to 1
+    printf("%d %f %s\n", 42, ratio, "done");
+
+    return sum(3, 1, (long)2, 3.5);
+}
@@ -0,0 +1,19 @@
+// The 7th and 8th integer arguments and the 9th double are passed on the stack
+// exit: 102
+long weighted(long a, long b, long c, long d, long e, long f, long g, long h)
+{
+    return a + 2 * b + 3 * c + 4 * d + 5 * e + 6 * f + 7 * g + 8 * h;
+}
+
+double mixed(int a, double b, int c, double d, double e, double f, double g, double h, double i, double j, double k)
+{
+    return a + b + c + d + e + f + g + h + i + j + k;
+}
+
+int main()
+{
+    long total = weighted(1, 1, 1, 1, 1, 1, 1, 1);
+    double other = mixed(1, 2.0, 3, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0);
+
+    return total + other;
+}
@@ -0,0 +1,17 @@
+// Build with --libc, so printf's buffer is flushed by exit
+// args: --libc
+// exit: 0
+// stdout: ./test was given 0 arguments
+#include <stdio.h>
+#include <stdlib.h>
+
+int main(int argc, char** argv, char** envp)
+{
+    printf("%s was given %d arguments\n", *argv, argc - 1);
+
+    if (argc > 1) {
+        exit(3);
+    }
+
+    return 0;
+}
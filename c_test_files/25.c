@@ -0,0 +1,17 @@
+// Build with -nostdlib, nothing is prepended so the program provides its own entry point
+// args: -nostdlib
+// exit: 7
+int main()
+{
+    return 7;
+}
+
+void _start()
+{
+    [[and rsp, -16]];
+    [[call main]];
+    [[mov rdi, rax]];
+    [[mov rax, 60]];
+    [[syscall]];
+    return;
+}
@@ -0,0 +1,30 @@
+// exit: 0
+// stdout: Hello 200 -42 ff z%
+// stdout: strcmp found them equal
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+
+#define GREETING "Hello"
+
+int main()
+{
+    char buffer[8];
+    memset(buffer, 0, sizeof(buffer));
+    memcpy(buffer, GREETING, strlen(GREETING));
+
+    uint8_t small = 200;
+    int64_t large = INT32_MAX;
+    bool same = strcmp(buffer, GREETING) == 0;
+
+    printf("%s %u %d %x %c%%\n", buffer, small, -42, 255, 'z');
+
+    if (same)
+    {
+        puts("strcmp found them equal");
+    }
+
+    return buffer == NULL;
+}
@@ -0,0 +1,38 @@
+// exit: 0
+// stdout: ok
+// stdout: open failed with ENOENT
+// stdout: close failed with errno 9
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <unistd.h>
+
+int main()
+{
+    putchar('o');
+    putchar('k');
+    putchar('\n');
+
+    int fd = open("/this/file/does/not/exist", O_RDONLY);
+    if (fd == -1)
+    {
+        if (errno == ENOENT)
+        {
+            puts("open failed with ENOENT");
+        }
+    }
+
+    if (close(-1) == -1)
+    {
+        printf("close failed with errno %d\n", errno);
+    }
+
+    char buffer[16];
+    long count = read(STDIN_FILENO, buffer, sizeof(buffer));
+    if (count > 0)
+    {
+        write(STDOUT_FILENO, buffer, count);
+    }
+
+    return 0;
+}
@@ -0,0 +1,22 @@
+// exit: 59
+int main()
+{
+    int a = 20;
+    int b = 22;
+    int sum = 0;
+    long counter = 5;
+
+    // %0 is sum, %1 and %2 are a and b, the text is kept as written
+    asm volatile ("mov %0, %1\n\t"
+                  "add %0, %2"
+                  : "=r"(sum)
+                  : "r"(a), "r"(b));
+
+    // Read and write counter in memory, with an immediate operand
+    asm ("add %0, %1" : "+m"(counter) : "i"(3 * 4));
+
+    // rbx is callee saved, so it is pushed and popped around the template
+    asm volatile ("mov rbx, [rsp]" ::: "rbx", "memory");
+
+    return sum + counter;
+}
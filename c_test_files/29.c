@@ -0,0 +1,19 @@
+// exit: 1
+// stdout: written by a raw system call
+int main()
+{
+    char* message = "written by a raw system call\n";
+
+    // write(1, message, 29), evaluating every argument before loading the registers
+    long written = __builtin_syscall3(1, 1, message, 29);
+
+    // getpid takes no arguments
+    long pid = __builtin_syscall0(39);
+
+    if (written != 29)
+    {
+        __builtin_syscall1(60, 1);
+    }
+
+    return pid > 0;
+}
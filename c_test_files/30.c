@@ -0,0 +1,23 @@
+// Build with -g, then `gdb ./test` and `break main`, `list` and `next` step through this file
+// args: -g
+// exit: 0
+// stdout: 9
+#include <stdio.h>
+
+int square(int value)
+{
+    return value * value;
+}
+
+int main()
+{
+    int a = 3;
+    int b = square(a);
+
+    if (b > a)
+    {
+        printf("%d\n", b);
+    }
+
+    return 0;
+}
@@ -0,0 +1,14 @@
+// Build with --annotate-asm, each of these lines appears as a comment in test.asm
+// args: --annotate-asm
+// exit: 0
+int add(int lhs, int rhs)
+{
+    return lhs + rhs;
+}
+
+int main()
+{
+    int total = add(2, 3);
+    if (total == 5) { total = 0; }
+    return total;
+}
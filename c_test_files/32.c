@@ -0,0 +1,23 @@
+// Pointer arithmetic moves by whole elements, exits with 0 when every check passes
+// exit: 0
+int main()
+{
+    int values[4];
+    int* first = values;
+
+    *first = 10;
+    *(first + 1) = 20;
+    *(2 + first) = 30;
+
+    int* last = first + 3;
+    *last = 40;
+
+    if (last - first != 3) { return 1; }
+    if (*(last - 1) != 30) { return 2; }
+
+    long words[2];
+    long* word = words;
+    if ((char*)(word + 1) - (char*)word != 8) { return 3; }
+
+    return 0;
+}
@@ -0,0 +1,15 @@
+// Integer literal suffixes, exits with 0 when every check passes
+// exit: 0
+int main()
+{
+    unsigned long largest = 18446744073709551615ul;
+    if (largest + 1 != 0) { return 1; }
+
+    // Unsigned, so -1u is the largest unsigned int rather than negative
+    if (-1u < 0) { return 2; }
+    if (sizeof(1u) != 4) { return 3; }
+    if (sizeof(1l) != 8 || sizeof(1LL) != 8 || sizeof(2147483648) != 8) { return 4; }
+
+    long big = 4000000000L;
+    return big != 4000000000;
+}
@@ -0,0 +1,35 @@
+// Floating point literals and arguments, exits with 0 when every check passes
+// exit: 0
+float half(float value)
+{
+    return value * 0.5f;
+}
+
+double scale(double value, float factor)
+{
+    return value * factor;
+}
+
+float first(float a, double b)
+{
+    return a;
+}
+
+double second(float a, double b)
+{
+    return b;
+}
+
+int main()
+{
+    if (sizeof(1.5f) != 4 || sizeof(1.5) != 8 || sizeof(1e3) != 8) { return 1; }
+
+    if (half(3.0f) != 1.5f) { return 2; }
+    if (scale(1e3, 2.5f) != 2500.0) { return 3; }
+    if (scale(2.5E-1, 4.0f) != 1.0) { return 4; }
+
+    if (first(0.25f, 8.0) != 0.25f) { return 5; }
+    if (second(0.25f, 8.0) != 8.0) { return 6; }
+
+    return 0;
+}
@@ -0,0 +1,28 @@
+// ++, --, += and -= on pointers move by whole elements, exits with 0 when every check passes
+// exit: 0
+int main()
+{
+    int values[4];
+    int* p = values;
+
+    *p++ = 1;
+    *p++ = 2;
+    *p = 3;
+    p += 1;
+    *p = 4;
+
+    if (p - values != 3) { return 1; }
+
+    p -= 2;
+    if (*p != 2) { return 2; }
+
+    --p;
+    if (*p != 1) { return 3; }
+
+    long words[2];
+    long* word = words;
+    ++word;
+    if ((char*)word - (char*)words != 8) { return 4; }
+
+    return 0;
+}
@@ -0,0 +1,19 @@
+// Build with -ffreestanding, only the entry point is prepended so this exits with 5
+// args: -ffreestanding
+// exit: 5
+#include <stddef.h>
+
+static size_t length(const char* string)
+{
+    if (*string == 0)
+    {
+        return 0;
+    }
+
+    return 1 + length(string + 1);
+}
+
+int main()
+{
+    return length("hello");
+}
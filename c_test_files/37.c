@@ -0,0 +1,16 @@
+// Octal and hexadecimal escapes give the byte with that value
+// exit: 0
+int main()
+{
+    char escape = '\033';
+    if (escape != 27) { return 1; }
+    if ('\x41' != 'A' || '\101' != 'A' || '\0' != 0) { return 2; }
+    if ('\xff' != -1) { return 3; }
+
+    // The "2" can't extend the \1 before it, escapes end with their literal
+    char* text = "\x48\151" "\x21" "\1" "2";
+    if (*text != 'H' || *(text + 1) != 'i' || *(text + 2) != '!') { return 4; }
+    if (*(text + 3) != 1 || *(text + 4) != '2' || *(text + 5) != 0) { return 5; }
+
+    return 0;
+}
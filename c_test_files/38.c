@@ -0,0 +1,19 @@
+// Casting a pointer to _Bool compares it with 0 rather than keeping its low byte
+// exit: 0
+int main()
+{
+    long words[33];
+    char* bytes = (char*)words;
+
+    // One of the first 256 bytes is at an address whose low byte is 0
+    char* aligned = bytes + (256 - (unsigned long)bytes % 256) % 256;
+    if ((unsigned char)(unsigned long)aligned != 0) { return 1; }
+
+    _Bool set = (_Bool)aligned;
+    if (!set) { return 2; }
+
+    char* none = 0;
+    if ((_Bool)none) { return 3; }
+
+    return 0;
+}
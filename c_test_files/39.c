@@ -0,0 +1,18 @@
+// A null pointer constant in one arm of ?: takes the type of the pointer in the other
+// exit: 0
+int main()
+{
+    int x = 5;
+    int* p = &x;
+
+    int* q = x ? 0 : p;
+    if (q != 0) { return 1; }
+
+    int* r = x ? p : 0;
+    if (*r != 5) { return 2; }
+
+    // The result is a pointer, so it isn't truncated to an int
+    if (sizeof(x ? 0 : p) != 8) { return 3; }
+
+    return 0;
+}
@@ -0,0 +1,14 @@
+// Dereferencing an array goes through the address of its first element
+// exit: 0
+int main()
+{
+    int values[2];
+    *values = 5;
+    if (*values != 5) { return 1; }
+
+    *(values + 1) = 7;
+    *values += 1;
+    if (*values + *(values + 1) != 13) { return 2; }
+
+    return 0;
+}
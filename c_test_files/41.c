@@ -0,0 +1,18 @@
+// A declaration may declare several names sharing a base type
+// exit: 0
+int first = 1, second, *pointer = &first;
+
+int main()
+{
+    int a, b = 2, *c = &b;
+    a = 3;
+
+    int values[2], count = 2;
+    *values = a;
+    *(values + 1) = *c;
+
+    if (*values + *(values + 1) != 5) { return 1; }
+    if (*pointer + second != 1 || count != 2) { return 2; }
+
+    return 0;
+}
@@ -0,0 +1,10 @@
+// sizeof a string literal measures the array, not a pointer to it
+// exit: 0
+int main()
+{
+    if (sizeof "abc" != 4) { return 1; }
+    if (sizeof("\x41\n") != 3) { return 2; }
+    if (sizeof "" != 1) { return 3; }
+
+    return 0;
+}
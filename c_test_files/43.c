@@ -0,0 +1,21 @@
+// Floating point comparisons with a NaN operand are false, except for !=
+// exit: 0
+int main()
+{
+    double zero = 0.0;
+    double nan = zero / zero;
+    double one = 1.0;
+
+    if (nan < one || nan <= one || nan > one || nan >= one) { return 1; }
+    if (one < nan || one <= nan || one > nan || one >= nan) { return 2; }
+    if (nan == nan || nan == one) { return 3; }
+    if (!(nan != nan)) { return 4; }
+
+    float half = 0.5f;
+    if (half == 0.5f && half <= 0.5f && half >= 0.5f && !(half < 0.5f) && half != 1.0f && half < 1.0f)
+    {
+        return 0;
+    }
+
+    return 5;
+}
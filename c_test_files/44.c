@@ -0,0 +1,27 @@
+// The string functions and malloc from the prelude
+// exit: 0
+#include <stdlib.h>
+#include <string.h>
+
+int main()
+{
+    if (strlen("") != 0 || strlen("hello") != 5) { return 1; }
+
+    char* buffer = malloc(100000);
+    if (buffer == 0) { return 2; }
+
+    // Long enough that recursing once per byte would overflow the stack
+    memset(buffer, 'a', 99999);
+    *(buffer + 99999) = 0;
+    if (strlen(buffer) != 99999) { return 3; }
+
+    memcpy(buffer, "abc", 4);
+    if (strcmp(buffer, "abc") != 0) { return 4; }
+    if (strcmp("abc", "abd") >= 0 || strcmp("abd", "abc") <= 0) { return 5; }
+    if (strcmp("ab", "abc") >= 0) { return 6; }
+    if (strcmp("\xff", "a") <= 0) { return 7; }
+
+    free(buffer);
+    free(0);
+    return 0;
+}
@@ -0,0 +1,30 @@
+// Arguments past the six general purpose and eight vector registers go on the stack
+// exit: 0
+long sum(long a, long b, long c, long d, long e, long f, long g, long h)
+{
+    return a + b * 2 + c * 3 + d * 4 + e * 5 + f * 6 + g * 7 + h * 8;
+}
+
+double mixed(int a, double b, char c, double d, double e, double f, double g, double h,
+             double i, double j, double k, int l, int m, int n, int o, int p, float q)
+{
+    return a + b + c + d + e + f + g + h + i + j + k + l + m + n + o + p + q;
+}
+
+// Odd and even numbers of stack arguments pad rsp differently
+int seventh(int a, int b, int c, int d, int e, int f, int g)
+{
+    return g;
+}
+
+int main()
+{
+    if (sum(1, 2, 3, 4, 5, 6, 7, 8) != 204) { return 1; }
+    if (seventh(0, 0, 0, 0, 0, 0, 42) != 42) { return 2; }
+    if (mixed(1, 2.0, 3, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12, 13, 14, 15, 16, 0.5f) != 136.5) { return 3; }
+
+    long (*pointer)(long, long, long, long, long, long, long, long) = sum;
+    if (pointer(8, 7, 6, 5, 4, 3, 2, 1) != 120) { return 4; }
+
+    return 0;
+}
@@ -0,0 +1,24 @@
+// Build with -g, then in gdb `break scale` and `run`, `print factor`, `print values`,
+// `print *name` and `print count` show the variables with their types
+// args: -g
+// exit: 4
+static int count = 3;
+
+double scale(double value, const double factor)
+{
+    volatile double scaled = value * factor;
+    return scaled;
+}
+
+int main()
+{
+    long values[4];
+    *(values + 2) = 3;
+    const char* name = "values";
+    void* nothing = 0;
+    double (*function)(double, const double) = scale;
+    static unsigned char calls = 0;
+
+    calls = calls + 1;
+    return function(*(values + 2), 2.0) + calls - count + (nothing != 0);
+}
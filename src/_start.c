@@ -0,0 +1,15 @@
+// The entry point, prepended unless linking with the system C runtime or -nostdlib
+void _start()
+{
+    // The kernel leaves argc on top of the stack, followed by the argv and envp arrays, each
+    // ending in a null pointer. They are read from above the frame pointer pushed on entry
+    [[mov rdi, [rbp + 8]]];
+    [[lea rsi, [rbp + 16]]];
+    [[lea rdx, [rsi + rdi * 8 + 8]]];
+    [[and rsp, -16]];
+    [[call main]];
+    [[mov rdi, rax]];
+    [[mov rax, 60]];
+    [[syscall]];
+    return;
+}
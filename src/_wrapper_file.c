@@ -1,50 +1,276 @@
-[[global _start]];
-[[extern malloc]];
+// This is a wrapper for normal C files, providing the parts of the C library in the bundled
+// headers. The entry point is in _start.c
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
 
-int* alloc(int bytes)
+static int error_number = 0;
+
+int* __errno_location()
 {
-    [[call malloc]];
-    return;
+    return &error_number;
 }
 
 void exit(int exit_code)
 {
-    [[mov rax, 60]];
-    [[syscall]];
+    __builtin_syscall1(60, exit_code);
     return;
 }
 
-void putchar(char* ptr)
+// System calls return -errno on failure, this turns that into -1 with errno set like the C
+// library wrappers
+static long syscall_result(long result)
 {
-    [[mov rax, 1]];
-    [[mov rsi, rdi]];
-    [[mov rdi, 0]];
-    [[mov rdx, 1]];
-    [[syscall]];
-    return;
+    if (result < 0 && result > -4096)
+    {
+        errno = -result;
+        return -1;
+    }
+
+    return result;
 }
 
-void print(char* ptr, int count)
+long read(int fd, void* buffer, size_t count)
 {
-    [[mov rax, 1]];
-    [[mov rdx, rsi]];
-    [[mov rsi, rdi]];
-    [[mov rdi, 0]];
-    [[syscall]];
-    return;
+    return syscall_result(__builtin_syscall3(0, fd, buffer, count));
 }
 
-void println(char* ptr, int count)
+long write(int fd, const void* buffer, size_t count)
 {
-    print(ptr, count);
-    char newline = 10;
-    putchar(&newline);
-    return;
+    return syscall_result(__builtin_syscall3(1, fd, buffer, count));
+}
+
+int open(const char* path, int flags, ...)
+{
+    int mode = 0;
+    if (flags & O_CREAT)
+    {
+        va_list arguments;
+        va_start(arguments, flags);
+        mode = va_arg(arguments, int);
+        va_end(arguments);
+    }
+
+    return syscall_result(__builtin_syscall3(2, path, flags, mode));
+}
+
+int close(int fd)
+{
+    return syscall_result(__builtin_syscall1(3, fd));
+}
+
+// There are no loops yet, so these use the string instructions and a loop written in asm
+
+size_t strlen(const char* string)
+{
+    size_t length = 0;
+    // rcx counts down from -1 once per byte scanned, the terminator included
+    asm volatile ("mov rdi, %1\n\t"
+                  "xor eax, eax\n\t"
+                  "mov rcx, -1\n\t"
+                  "repne scasb\n\t"
+                  "not rcx\n\t"
+                  "dec rcx\n\t"
+                  "mov %0, rcx"
+                  : "=r"(length)
+                  : "r"(string)
+                  : "rax", "rcx", "rsi", "rdi", "memory");
+    return length;
 }
 
-// This is a wrapper for normal C files
-void _start()
+void* memcpy(void* destination, const void* source, size_t count)
 {
-    exit(main());
+    asm volatile ("mov rdi, %0\n\t"
+                  "mov rsi, %1\n\t"
+                  "mov rcx, %2\n\t"
+                  "rep movsb"
+                  :
+                  : "r"(destination), "r"(source), "r"(count)
+                  : "rcx", "rsi", "rdi", "memory");
+    return destination;
+}
+
+void* memset(void* destination, int value, size_t count)
+{
+    asm volatile ("mov rdi, %0\n\t"
+                  "mov eax, %1\n\t"
+                  "mov rcx, %2\n\t"
+                  "rep stosb"
+                  :
+                  : "r"(destination), "r"(value), "r"(count)
+                  : "rax", "rcx", "rsi", "rdi", "memory");
+    return destination;
+}
+
+int strcmp(const char* lhs, const char* rhs)
+{
+    int difference = 0;
+    asm volatile ("mov rsi, %1\n\t"
+                  "mov rdi, %2\n"
+                  ".strcmp_next:\n\t"
+                  "movzx eax, byte [rsi]\n\t"
+                  "movzx ecx, byte [rdi]\n\t"
+                  "cmp eax, ecx\n\t"
+                  "jne .strcmp_done\n\t"
+                  "inc rsi\n\t"
+                  "inc rdi\n\t"
+                  "test eax, eax\n\t"
+                  "jnz .strcmp_next\n"
+                  ".strcmp_done:\n\t"
+                  "sub eax, ecx\n\t"
+                  "mov %0, eax"
+                  : "=r"(difference)
+                  : "r"(lhs), "r"(rhs)
+                  : "rax", "rcx", "rsi", "rdi", "memory");
+    return difference;
+}
+
+// Every allocation is its own anonymous mapping, its length being kept in the 16 bytes before
+// the pointer returned so free can unmap it
+void* malloc(size_t size)
+{
+    size_t length = size + 16;
+    if (length < size)
+    {
+        errno = ENOMEM;
+        return 0;
+    }
+
+    // PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS
+    long address = syscall_result(__builtin_syscall6(9, 0, length, 3, 34, -1, 0));
+    if (address == -1)
+    {
+        return 0;
+    }
+
+    size_t* header = (size_t*)address;
+    *header = length;
+    return (char*)address + 16;
+}
+
+void free(void* pointer)
+{
+    if (pointer == 0)
+    {
+        return;
+    }
+
+    size_t* header = (size_t*)((char*)pointer - 16);
+    __builtin_syscall2(11, header, *header);
     return;
-}
\ No newline at end of file
+}
+
+int putchar(int character)
+{
+    unsigned char byte = character;
+    if (write(STDOUT_FILENO, &byte, 1) != 1)
+    {
+        return EOF;
+    }
+
+    return byte;
+}
+
+int puts(const char* string)
+{
+    size_t length = strlen(string);
+    if (write(STDOUT_FILENO, string, length) != length)
+    {
+        return EOF;
+    }
+
+    return putchar('\n');
+}
+
+static int print_unsigned(unsigned long value, unsigned int base)
+{
+    int written = 0;
+    if (value >= base)
+    {
+        written = print_unsigned(value / base, base);
+    }
+
+    char digit = *("0123456789abcdef" + value % base);
+    write(1, &digit, 1);
+    return written + 1;
+}
+
+static int print_signed(long value)
+{
+    if (value < 0)
+    {
+        write(1, "-", 1);
+        return 1 + print_unsigned(-value, 10);
+    }
+
+    return print_unsigned(value, 10);
+}
+
+static int print_format(const char* format, va_list arguments)
+{
+    if (*format == 0)
+    {
+        return 0;
+    }
+
+    if (*format != '%')
+    {
+        write(1, format, 1);
+        return 1 + print_format(format + 1, arguments);
+    }
+
+    char specifier = *(format + 1);
+    int written = 0;
+
+    // A lone % at the end prints nothing
+    if (specifier == 0)
+    {
+        return 0;
+    }
+
+    if (specifier == 'd' || specifier == 'i')
+    {
+        written = print_signed(va_arg(arguments, int));
+    }
+
+    if (specifier == 'u')
+    {
+        written = print_unsigned(va_arg(arguments, unsigned int), 10);
+    }
+
+    if (specifier == 'x')
+    {
+        written = print_unsigned(va_arg(arguments, unsigned int), 16);
+    }
+
+    if (specifier == 'c')
+    {
+        char character = va_arg(arguments, int);
+        written = write(1, &character, 1);
+    }
+
+    if (specifier == 's')
+    {
+        const char* string = va_arg(arguments, const char*);
+        written = write(1, string, strlen(string));
+    }
+
+    if (specifier == '%')
+    {
+        written = write(1, "%", 1);
+    }
+
+    return written + print_format(format + 2, arguments);
+}
+
+int printf(const char* format, ...)
+{
+    va_list arguments;
+    va_start(arguments, format);
+    int written = print_format(format, arguments);
+    va_end(arguments);
+    return written;
+}
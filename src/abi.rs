@@ -0,0 +1,88 @@
+use crate::parse::Type;
+
+/// General purpose argument registers, in the order they are assigned
+pub const INTEGER_REGISTERS: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+/// Vector argument registers, in the order they are assigned
+pub const SSE_REGISTERS: [&str; 8] = ["xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5", "xmm6", "xmm7"];
+
+/// Linux system call argument registers, the number goes in `rax` and the result comes back in it
+pub const SYSCALL_REGISTERS: [&str; 6] = ["rdi", "rsi", "rdx", "r10", "r8", "r9"];
+
+/// How the System V x86-64 ABI passes a value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Class {
+    /// In the next general purpose register
+    Integer,
+    /// In the next vector register
+    Sse,
+    /// Copied to the stack, or returned through a hidden pointer
+    Memory,
+}
+
+/// Classifies a value of type `ty`. Every scalar fits in one eightbyte, there being no
+/// aggregates that could be split across two
+pub fn classify(ty: &Type) -> Class {
+    let ty = ty.unqualified();
+
+    if ty.is_floating() {
+        Class::Sse
+    } else if ty.is_integer() || ty.is_pointer() {
+        Class::Integer
+    } else {
+        Class::Memory
+    }
+}
+
+/// Where an argument is passed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    Integer(&'static str),
+    Sse(&'static str),
+    /// Offset from the first stack argument, which is at `rsp` when the call is made
+    Stack(i64),
+}
+
+/// The registers and stack space taken by a list of arguments
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Usage {
+    pub integer: usize,
+    pub sse: usize,
+    /// Bytes of arguments passed on the stack, each taking one eightbyte
+    pub stack: i64,
+}
+
+/// Assigns each argument a register or stack slot, left to right. Once a class runs out of
+/// registers its arguments go on the stack, later arguments of the other class still taking
+/// registers
+pub fn locations(arguments: &[Type]) -> (Vec<Location>, Usage) {
+    let mut usage = Usage::default();
+
+    let locations = arguments
+        .iter()
+        .map(|ty| match classify(ty) {
+            Class::Integer if usage.integer < INTEGER_REGISTERS.len() => {
+                usage.integer += 1;
+                Location::Integer(INTEGER_REGISTERS[usage.integer - 1])
+            }
+            Class::Sse if usage.sse < SSE_REGISTERS.len() => {
+                usage.sse += 1;
+                Location::Sse(SSE_REGISTERS[usage.sse - 1])
+            }
+            class => {
+                if class == Class::Memory {
+                    eprintln!("Error: {ty:?} can't be passed by value, only scalars and pointers can");
+                    panic!()
+                }
+                usage.stack += 8;
+                Location::Stack(usage.stack - 8)
+            }
+        })
+        .collect();
+
+    (locations, usage)
+}
+
+/// Counts the registers and stack used by arguments of the given types
+pub fn usage(arguments: &[Type]) -> Usage {
+    locations(arguments).1
+}
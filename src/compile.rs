@@ -1,56 +1,1561 @@
-use std::fmt::format;
+use std::collections::{HashMap, HashSet};
 
 use low_level_ir::*;
 
-use crate::parse::{ASTNode, ASTValue};
+use crate::{abi, dwarf::{self, DebugInfo}, fold, tokenise::escaped_char};
+use crate::parse::{binary_node, ASTNode, ASTValue, Comparison, StorageClass, Type};
 
-fn compile_value(value: ASTNode, compiler: &mut Compiler) -> Value {
-    if let ASTNode::Add(lhs, rhs) = value {
-        return Value::Add(
-            Box::new(compile_value(*lhs, compiler)),
-            Box::new(compile_value(*rhs, compiler)),
+/// Settings for the whole translation unit
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// Describes the lines, functions, variables and types to debuggers in DWARF
+    pub debug_info: bool,
+    /// Writes the source lines as comments before the code generated for them
+    pub annotate_asm: bool,
+    /// The lines of every source file, by name, for `annotate_asm`
+    pub sources: HashMap<String, Vec<String>>,
+    /// The file being compiled, which names the compile unit in the debug info
+    pub file: String,
+}
+
+pub struct CompileState {
+    pub compiler: Compiler,
+    options: CompileOptions,
+    /// The last source line annotated, so each is only written once
+    annotated: Option<(String, u32)>,
+    /// Types of every function in the translation unit, collected before compiling bodies
+    functions: HashMap<String, Type>,
+    /// Types of file scope variables and the labels of static locals
+    globals: HashMap<String, Type>,
+    /// Functions and variables given storage in this translation unit, anything else is extern
+    defined: HashSet<String>,
+    /// Symbols already declared `extern` in the output
+    externs: HashSet<String>,
+    /// Types of the parameters and locals of the function currently being compiled
+    variables: HashMap<String, Type>,
+    /// Labels of the static locals of the function currently being compiled
+    statics: HashMap<String, String>,
+    /// File scope operands produced while compiling a function, such as static locals
+    file_operands: Vec<Operand>,
+    /// Contents of every distinct string literal, the index giving its `_SD{n}` label
+    strings: Vec<String>,
+    /// The `gp_offset`, `fp_offset` and stack offset `va_start` begins at, if the function
+    /// currently being compiled is variadic
+    va_offsets: Option<(i64, i64, i64)>,
+    return_type: Type,
+    temporaries: usize,
+    /// Collected for `debug_info`
+    debug: DebugInfo,
+}
+
+impl CompileState {
+    pub fn new(options: CompileOptions) -> Self {
+        Self {
+            compiler: Compiler::new(),
+            options,
+            annotated: None,
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            defined: HashSet::new(),
+            externs: HashSet::new(),
+            variables: HashMap::new(),
+            statics: HashMap::new(),
+            file_operands: vec![],
+            strings: vec![],
+            va_offsets: None,
+            return_type: Type::VOID,
+            temporaries: 0,
+            debug: DebugInfo::default(),
+        }
+    }
+
+    /// Comments with the source lines up to `line`, starting after the last one annotated when
+    /// that was earlier in the same file so declarations and braces are shown too
+    fn annotate(&mut self, file: &str, line: u32) -> Vec<Operand> {
+        let first = match &self.annotated {
+            Some((last_file, last)) if last_file == file && *last >= line => return vec![],
+            Some((last_file, last)) if last_file == file => last + 1,
+            _ => line,
+        };
+        self.annotated = Some((file.to_string(), line));
+
+        let Some(lines) = self.options.sources.get(file) else {
+            return vec![];
+        };
+
+        (first..=line)
+            .filter_map(|number| {
+                let text = lines.get(number as usize - 1)?.trim_end();
+                (!text.trim().is_empty()).then(|| Operand::InlineAssembly(format!("; {file}:{number}: {text}")))
+            })
+            .collect()
+    }
+
+    /// Describes a parameter or local to the debugger, storing its offset from `rbp` where the
+    /// debug info reads it as the layout of the frame is up to the IR
+    fn describe_local(&mut self, name: &str, ty: &Type, parameter: bool) -> Vec<Operand> {
+        if !self.options.debug_info {
+            return vec![];
+        }
+
+        let slot = self.debug.frame_slot();
+        self.file_operands.push(Operand::DeclareGlobal(Type::LONG.into_ir(), slot.clone(), Value::Null));
+        self.debug.variable(name, ty, dwarf::Location::Frame(slot.clone()), parameter);
+        vec![Operand::SetValue(
+            Value::Variable(slot),
+            Value::Sub(
+                Box::new(Value::Reference(name.to_string())),
+                Box::new(Value::Register("rbp".to_string())),
+            ),
+        )]
+    }
+
+    /// Collects the types of every file scope function and variable, before any body is compiled
+    pub(crate) fn declare_file_scope(&mut self, ast: &[ASTNode]) {
+        for node in ast {
+            match node {
+                ASTNode::FunctionDeclaration(ty, name, _, params, variadic, _) => {
+                    let params = params.iter().map(|(_, ty)| ty.clone()).collect();
+                    self.functions
+                        .insert(name.clone(), Type::FUNC(Box::new(ty.clone()), params, *variadic));
+                    self.defined.insert(name.clone());
+                }
+                ASTNode::FunctionPrototype(ty, name, _) => {
+                    self.functions.entry(name.clone()).or_insert_with(|| ty.clone());
+                }
+                ASTNode::VariableDeclaration(ty, name, _, storage) => {
+                    self.globals.insert(name.clone(), ty.clone());
+                    if *storage != StorageClass::Extern {
+                        self.defined.insert(name.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Brings a function's parameters into scope in place of the previous function's locals
+    pub(crate) fn enter_function(&mut self, params: &[(String, Type)]) {
+        self.variables = params.iter().cloned().collect();
+        self.statics.clear();
+    }
+
+    /// Brings a local into scope, for walking a body without compiling it
+    pub(crate) fn declare_local(&mut self, name: &str, ty: &Type) {
+        self.variables.insert(name.to_string(), ty.clone());
+    }
+
+    /// The assembly level name of a variable, static locals live under a unique label
+    fn resolve(&self, name: &str) -> String {
+        match self.statics.get(name) {
+            Some(label) => label.clone(),
+            None => name.to_string(),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.variables
+            .get(name)
+            .or_else(|| self.statics.get(name).and_then(|label| self.globals.get(label)))
+            .or_else(|| self.globals.get(name))
+            .or_else(|| self.functions.get(name))
+    }
+
+    /// Declares a compiler generated local, used to avoid evaluating an expression twice
+    fn temporary(&mut self, ty: Type) -> String {
+        let name = format!("_T{}", self.temporaries);
+        self.temporaries += 1;
+        self.variables.insert(name.clone(), ty);
+        name
+    }
+
+    fn is_function(&self, name: &str) -> bool {
+        self.lookup(name).is_some_and(|ty| ty.is_function())
+    }
+
+    fn variable_type(&self, name: &str) -> Type {
+        match self.lookup(name) {
+            Some(ty) => ty.clone(),
+            None => {
+                eprintln!("Error: {name} is not declared");
+                panic!()
+            }
+        }
+    }
+
+    /// Declares a symbol defined in another translation unit, at most once
+    fn declare_extern(&mut self, name: &str) -> Vec<Operand> {
+        if self.defined.contains(name) || !self.externs.insert(name.to_string()) {
+            return vec![];
+        }
+
+        vec![Operand::InlineAssembly(format!("extern {name}"))]
+    }
+
+    /// The label of a string literal, identical literals share a single copy
+    fn intern_string(&mut self, value: String) -> String {
+        let index = match self.strings.iter().position(|s| *s == value) {
+            Some(index) => index,
+            None => {
+                self.strings.push(value);
+                self.strings.len() - 1
+            }
+        };
+        format!("_SD{index}")
+    }
+
+    fn call_type(&self, callee: &Type) -> Type {
+        match callee.unqualified() {
+            Type::FUNC(return_type, _, _) => *return_type.clone(),
+            Type::PTR(inner) if inner.is_function() => self.call_type(inner),
+            _ => {
+                eprintln!("Error: {callee:?} is not callable");
+                panic!()
+            }
+        }
+    }
+
+    fn pointee_type(&self, name: &str) -> Type {
+        match self.variable_type(name).unqualified().clone() {
+            Type::PTR(inner) | Type::ARRAY(inner, _) => *inner,
+            ty => {
+                eprintln!("Error: Cannot dereference {name} of type {ty:?}");
+                panic!()
+            }
+        }
+    }
+
+    fn integer_type_of(&self, node: &ASTNode) -> Type {
+        let ty = self.type_of(node);
+        if !ty.is_integer() {
+            eprintln!("Error: Expected an integer operand, got {ty:?} instead");
+            panic!()
+        }
+        ty
+    }
+
+    /// The type of an expression before array decay, which is what `sizeof` measures
+    fn object_type(&self, node: &ASTNode) -> Type {
+        match node {
+            ASTNode::Value(ASTValue::StringLiteral(name)) => self.variable_type(name),
+            // A string literal is an array of its bytes and the terminator
+            ASTNode::Value(ASTValue::StringValue(value)) => {
+                Type::ARRAY(Box::new(Type::CHAR), string_bytes(value).len() as u32 + 1)
+            }
+            ASTNode::Value(ASTValue::Deref(name)) => self.pointee_type(name),
+            ASTNode::Deref(pointer) => match self.type_of(pointer) {
+                Type::PTR(inner) => *inner,
+                ty => {
+                    eprintln!("Error: Cannot dereference a value of type {ty:?}");
+                    panic!()
+                }
+            },
+            node => self.type_of(node),
+        }
+    }
+
+    /// The type of an expression, after array and function decay
+    pub fn type_of(&self, node: &ASTNode) -> Type {
+        match node {
+            ASTNode::Value(value) => match value {
+                ASTValue::IntValue(value) => fold::literal_type(*value),
+                ASTValue::FloatValue(_) => Type::DOUBLE,
+                ASTValue::CharValue(_) => Type::CHAR,
+                ASTValue::StringValue(_) => Type::PTR(Box::new(Type::CHAR)),
+                // The value of an lvalue doesn't carry its qualifiers
+                ASTValue::StringLiteral(name) => self.variable_type(name).unqualified().clone().decay(),
+                ASTValue::Deref(name) => self.pointee_type(name).unqualified().clone().decay(),
+                ASTValue::Ref(name) => Type::PTR(Box::new(self.variable_type(name))),
+                ASTValue::FunctionCall(name, _)
+                    if syscall_builtin(name).is_some() && self.lookup(name).is_none() =>
+                {
+                    Type::LONG
+                }
+                // Calls to undeclared functions are implicitly declared as returning int
+                ASTValue::FunctionCall(name, _) => match self.lookup(name) {
+                    Some(ty) => self.call_type(ty),
+                    None => Type::INT,
+                },
+                ASTValue::IndirectCall(callee, _) => self.call_type(&self.type_of(callee)),
+            },
+            ASTNode::Add(lhs, rhs) | ASTNode::Sub(lhs, rhs) => {
+                let lhs = self.type_of(lhs);
+                let rhs = self.type_of(rhs);
+
+                match (lhs.is_pointer(), rhs.is_pointer()) {
+                    (true, true) => Type::LONG,
+                    (true, false) => lhs,
+                    (false, true) => rhs,
+                    (false, false) => lhs.common(&rhs),
+                }
+            }
+            ASTNode::Mul(lhs, rhs) | ASTNode::Div(lhs, rhs) => {
+                self.type_of(lhs).common(&self.type_of(rhs))
+            }
+            ASTNode::Compare(_) | ASTNode::And(_, _) | ASTNode::Or(_, _) | ASTNode::Not(_) => Type::INT,
+            ASTNode::Deref(pointer) => match self.type_of(pointer) {
+                Type::PTR(inner) => inner.unqualified().clone().decay(),
+                ty => {
+                    eprintln!("Error: Cannot dereference a value of type {ty:?}");
+                    panic!()
+                }
+            },
+            ASTNode::Increment { lvalue, .. }
+            | ASTNode::SetVariable(lvalue, _)
+            | ASTNode::CompoundAssignment(lvalue, _, _) => self.type_of(lvalue),
+            // A null pointer constant in one arm takes the type of a pointer in the other, and a
+            // void pointer makes the result one too, see C11 6.5.15
+            ASTNode::Ternary(_, main, other) => {
+                let main_type = self.type_of(main);
+                let other_type = self.type_of(other);
+                match (main_type.is_pointer(), other_type.is_pointer()) {
+                    _ if main_type.is_arithmetic() && other_type.is_arithmetic() => {
+                        main_type.common(&other_type)
+                    }
+                    (true, false) if fold::is_null_pointer_constant(other) => main_type,
+                    (false, true) if fold::is_null_pointer_constant(main) => other_type,
+                    (true, true) if other_type == Type::PTR(Box::new(Type::VOID)) => other_type,
+                    (true, true) => main_type,
+                    _ if main_type == other_type => main_type,
+                    _ => {
+                        eprintln!("Error: Type mismatch in conditional expression, {main_type:?} and {other_type:?}");
+                        panic!()
+                    }
+                }
+            }
+            ASTNode::Comma(_, rhs) => self.type_of(rhs),
+            ASTNode::Cast(ty, _) | ASTNode::VaArg(_, ty) => ty.unqualified().clone(),
+            ASTNode::VaStart(_) | ASTNode::VaEnd(_) => Type::VOID,
+            // size_t
+            ASTNode::SizeOf(_) | ASTNode::SizeOfType(_) | ASTNode::AlignOfType(_) => Type::ULONG,
+            ASTNode::BitAnd(lhs, rhs) | ASTNode::BitOr(lhs, rhs) | ASTNode::BitXor(lhs, rhs) => {
+                let lhs = self.integer_type_of(lhs);
+                lhs.common(&self.integer_type_of(rhs))
+            }
+            ASTNode::BitNot(value) => self.integer_type_of(value).promote(),
+            // The type of a shift is the promoted left operand, the right operand only gives the count
+            ASTNode::ShiftLeft(lhs, rhs) | ASTNode::ShiftRight(lhs, rhs) => {
+                self.integer_type_of(rhs);
+                self.integer_type_of(lhs).promote()
+            }
+            ASTNode::Mod(lhs, rhs) => {
+                let ty = self.type_of(lhs).common(&self.type_of(rhs));
+                if ty.is_floating() {
+                    eprintln!("Error: Invalid operands to %, {ty:?} is not an integer type");
+                    panic!()
+                }
+                ty
+            }
+            _ => {
+                eprintln!("Expected a value; Recieved {node:#?} instead");
+                panic!()
+            }
+        }
+    }
+}
+
+/// Converts an arithmetic value between types, sign or zero extending integers depending on `from`
+fn convert(value: Value, from: &Type, to: &Type) -> Value {
+    if !from.is_arithmetic() || !to.is_arithmetic() || from.unqualified() == to.unqualified() {
+        return value;
+    }
+
+    // Integer literals are untyped in the IR and same width integer conversions keep the bit pattern
+    if from.is_integer()
+        && to.is_integer()
+        && (matches!(value, Value::Int(_) | Value::Char(_)) || from.bytes() == to.bytes())
+    {
+        return value;
+    }
+
+    Value::Cast(Box::new(value), from.into_ir(), to.into_ir())
+}
+
+/// Floating point constants are loaded from the data section, as SSE has no immediate operands
+fn compile_float(value: f64, ty: &Type, compiler: &mut CompileState) -> Value {
+    let define_name = format!("_FD{}", compiler.compiler.float_defines.len());
+    compiler
+        .compiler
+        .float_defines
+        .push((define_name.clone(), ty.into_ir(), value));
+    Value::FloatLiteral(define_name)
+}
+
+fn compile_value_as(value: ASTNode, ty: &Type, compiler: &mut CompileState) -> Value {
+    let ty = ty.unqualified();
+
+    // Constants are emitted directly in the destination type
+    if let Some(constant) = fold::evaluate(&value) {
+        return if ty.is_floating() {
+            compile_float(constant.as_f64(), ty, compiler)
+        } else if ty.is_integer() {
+            Value::Int(constant.convert(ty).value.to_string())
+        } else {
+            Value::Int(constant.value.to_string())
+        };
+    }
+
+    match value {
+        ASTNode::Value(ASTValue::FloatValue(value)) if ty.is_floating() => {
+            return compile_float(value, ty, compiler)
+        }
+        ASTNode::Value(ASTValue::FloatValue(value)) if ty.is_integer() => {
+            return Value::Int((value as i64).to_string())
+        }
+        _ => {}
+    }
+
+    let from = compiler.type_of(&value);
+    check_qualifiers(&from, ty);
+
+    // Converting a scalar to _Bool yields whether it compares unequal to 0
+    if *ty == Type::BOOL && from != Type::BOOL {
+        return Value::Compare(Box::new(compile_condition(value, compiler)));
+    }
+
+    let value = compile_value(value, compiler);
+    convert(value, &from, ty)
+}
+
+/// A pointer conversion may add qualifiers to the pointed to type but not discard them, as
+/// `char* p = (const char*)s` would allow writing through `p`
+fn check_qualifiers(from: &Type, to: &Type) {
+    let (Type::PTR(from_inner), Type::PTR(to_inner)) = (from.unqualified(), to.unqualified()) else {
+        return;
+    };
+
+    if (from_inner.is_const() && !to_inner.is_const())
+        || (from_inner.is_volatile() && !to_inner.is_volatile())
+    {
+        eprintln!("Error: Conversion from {from:?} to {to:?} discards qualifiers");
+        panic!()
+    }
+}
+
+/// Compiles both operands converted to their common type, returning the common type
+fn compile_arithmetic(
+    lhs: ASTNode,
+    rhs: ASTNode,
+    compiler: &mut CompileState,
+) -> (Type, Box<Value>, Box<Value>) {
+    let lhs_type = compiler.type_of(&lhs);
+    let rhs_type = compiler.type_of(&rhs);
+
+    // Pointer comparisons are done on the raw, unsigned addresses, `+` and `-` are scaled by
+    // `compile_pointer_arithmetic` before getting here
+    if lhs_type.is_pointer() || rhs_type.is_pointer() {
+        return (
+            Type::ULONG,
+            Box::new(compile_value(lhs, compiler)),
+            Box::new(compile_value(rhs, compiler)),
         );
     }
 
-    if let ASTNode::Sub(lhs, rhs) = value {
-        return Value::Sub(
-            Box::new(compile_value(*lhs, compiler)),
-            Box::new(compile_value(*rhs, compiler)),
+    if !lhs_type.is_arithmetic() || !rhs_type.is_arithmetic() {
+        eprintln!("Error: Invalid operands of type {lhs_type:?} and {rhs_type:?}");
+        panic!()
+    }
+
+    let ty = lhs_type.common(&rhs_type);
+    let lhs = Box::new(compile_value_as(lhs, &ty, compiler));
+    let rhs = Box::new(compile_value_as(rhs, &ty, compiler));
+    (ty, lhs, rhs)
+}
+
+/// The size of what a pointer points to, `void*` moving a byte at a time like in GNU C
+fn element_size(pointer: &Type) -> i64 {
+    match pointer.unqualified() {
+        Type::PTR(inner) if *inner.unqualified() == Type::VOID => 1,
+        Type::PTR(inner) if inner.is_function() => {
+            eprintln!("Error: Arithmetic on a pointer to a function, {pointer:?}");
+            panic!()
+        }
+        Type::PTR(inner) => inner.bytes() as i64,
+        _ => unreachable!(),
+    }
+}
+
+/// `pointer + n` and `pointer - n` move by `n` elements and `pointer - pointer` counts the
+/// elements between them, so the integer side is scaled by the size of the pointed to type
+fn compile_pointer_arithmetic(lhs: ASTNode, rhs: ASTNode, subtract: bool, compiler: &mut CompileState) -> Value {
+    let lhs_type = compiler.type_of(&lhs);
+    let rhs_type = compiler.type_of(&rhs);
+
+    let scale = |value: ASTNode, size: i64, compiler: &mut CompileState| -> Box<Value> {
+        if !compiler.type_of(&value).is_integer() {
+            eprintln!("Error: Invalid operands of type {lhs_type:?} and {rhs_type:?}");
+            panic!()
+        }
+
+        let value = compile_value_as(value, &Type::LONG, compiler);
+        match size {
+            1 => Box::new(value),
+            size => Box::new(Value::Mul(Box::new(value), Box::new(Value::Int(size.to_string())))),
+        }
+    };
+
+    match (lhs_type.is_pointer(), rhs_type.is_pointer(), subtract) {
+        (true, true, true) => {
+            let size = element_size(&lhs_type);
+            if element_size(&rhs_type) != size {
+                eprintln!("Error: Subtracting pointers to different types, {lhs_type:?} and {rhs_type:?}");
+                panic!()
+            }
+
+            let difference = Value::Sub(Box::new(compile_value(lhs, compiler)), Box::new(compile_value(rhs, compiler)));
+            match size {
+                1 => difference,
+                size => Value::Div(Box::new(difference), Box::new(Value::Int(size.to_string()))),
+            }
+        }
+        (true, false, _) => {
+            let size = element_size(&lhs_type);
+            let pointer = Box::new(compile_value(lhs, compiler));
+            let offset = scale(rhs, size, compiler);
+            if subtract {
+                Value::Sub(pointer, offset)
+            } else {
+                Value::Add(pointer, offset)
+            }
+        }
+        (false, true, false) => {
+            let offset = scale(lhs, element_size(&rhs_type), compiler);
+            Value::Add(offset, Box::new(compile_value(rhs, compiler)))
+        }
+        _ => {
+            eprintln!("Error: Invalid operands of type {lhs_type:?} and {rhs_type:?}");
+            panic!()
+        }
+    }
+}
+
+fn compile_bitwise(
+    lhs: ASTNode,
+    rhs: ASTNode,
+    compiler: &mut CompileState,
+) -> (Type, Box<Value>, Box<Value>) {
+    compiler.integer_type_of(&lhs);
+    compiler.integer_type_of(&rhs);
+    compile_arithmetic(lhs, rhs, compiler)
+}
+
+/// Shifts don't convert to a common type, the count is always an `int` as it ends up in `cl`
+fn compile_shift(
+    lhs: ASTNode,
+    rhs: ASTNode,
+    compiler: &mut CompileState,
+) -> (Type, Box<Value>, Box<Value>) {
+    let ty = compiler.integer_type_of(&lhs).promote();
+    compiler.integer_type_of(&rhs);
+
+    let lhs = Box::new(compile_value_as(lhs, &ty, compiler));
+    let rhs = Box::new(compile_value_as(rhs, &Type::INT, compiler));
+    (ty, lhs, rhs)
+}
+
+/// Reduces an lvalue to a variable or a dereferenced variable so it can be read and written
+/// without being evaluated again, returning the operands that have to run first
+fn compile_lvalue(lvalue: ASTNode, compiler: &mut CompileState) -> (Vec<Operand>, ASTValue) {
+    match lvalue {
+        // An array is dereferenced through a pointer to its first element
+        ASTNode::Value(ASTValue::Deref(name)) if matches!(compiler.lookup(&name), Some(Type::ARRAY(_, _))) => {
+            let array = ASTNode::Value(ASTValue::StringLiteral(name));
+            compile_lvalue(ASTNode::Deref(Box::new(array)), compiler)
+        }
+        ASTNode::Value(value @ (ASTValue::StringLiteral(_) | ASTValue::Deref(_))) => {
+            (vec![], value)
+        }
+        ASTNode::Deref(pointer) => {
+            let ty = compiler.type_of(&pointer);
+            let value = compile_value(*pointer, compiler);
+            let name = compiler.temporary(ty.clone());
+            (
+                vec![Operand::DeclareVariable(ty.into_ir(), name.clone(), value)],
+                ASTValue::Deref(name),
+            )
+        }
+        lvalue => {
+            eprintln!("Error: {lvalue:?} is not assignable");
+            panic!()
+        }
+    }
+}
+
+fn check_writable(lvalue: &ASTValue, compiler: &CompileState) {
+    let ty = compiler.object_type(&ASTNode::Value(lvalue.clone()));
+    if ty.is_const() {
+        eprintln!("Error: Cannot assign to {lvalue:?} of type {ty:?}, it is read only");
+        panic!()
+    }
+}
+
+/// Compiles `lvalue = value`, returning the operands and the assigned lvalue
+fn compile_assignment(
+    lvalue: ASTNode,
+    value: ASTNode,
+    compiler: &mut CompileState,
+) -> (Vec<Operand>, ASTValue) {
+    let (mut statements, lvalue) = compile_lvalue(lvalue, compiler);
+    check_writable(&lvalue, compiler);
+    let ty = compiler.type_of(&ASTNode::Value(lvalue.clone()));
+
+    let value = compile_value_as(value, &ty, compiler);
+    statements.push(Operand::SetValue(
+        compile_astvalue(lvalue.clone(), compiler),
+        value,
+    ));
+
+    (statements, lvalue)
+}
+
+/// Compiles `lvalue op= value`, returning the operands and the updated lvalue
+fn compile_compound_assignment(
+    lvalue: ASTNode,
+    operation: &str,
+    value: ASTNode,
+    compiler: &mut CompileState,
+) -> (Vec<Operand>, ASTValue) {
+    let (mut statements, lvalue) = compile_lvalue(lvalue, compiler);
+    check_writable(&lvalue, compiler);
+    let ty = compiler.type_of(&ASTNode::Value(lvalue.clone()));
+
+    // `p += n` and `p -= n` go through the scaled pointer arithmetic of `+` and `-`
+    if ty.is_pointer() && operation != "+" && operation != "-" {
+        eprintln!("Error: Invalid operator {operation}= on a pointer of type {ty:?}");
+        panic!()
+    }
+
+    let value = binary_node(operation, ASTNode::Value(lvalue.clone()), value);
+    let value = compile_value_as(value, &ty, compiler);
+    statements.push(Operand::SetValue(
+        compile_astvalue(lvalue.clone(), compiler),
+        value,
+    ));
+
+    (statements, lvalue)
+}
+
+/// Compiles `++` and `--`, returning the operands and the value of the expression
+fn compile_increment(
+    lvalue: ASTNode,
+    decrement: bool,
+    postfix: bool,
+    compiler: &mut CompileState,
+) -> (Vec<Operand>, Value) {
+    let (mut statements, lvalue) = compile_lvalue(lvalue, compiler);
+    check_writable(&lvalue, compiler);
+    let ty = compiler.type_of(&ASTNode::Value(lvalue.clone()));
+
+    let result = if postfix {
+        let previous = compile_astvalue(lvalue.clone(), compiler);
+        let name = compiler.temporary(ty.clone());
+        statements.push(Operand::DeclareVariable(
+            ty.into_ir(),
+            name.clone(),
+            previous,
+        ));
+        Value::Variable(name)
+    } else {
+        compile_astvalue(lvalue.clone(), compiler)
+    };
+
+    // A pointer moves by one element, as `+` and `-` scale by the size of the pointed to type
+    let one = ASTNode::Value(ASTValue::IntValue(1));
+    let operation = if decrement { "-" } else { "+" };
+    let value = binary_node(operation, ASTNode::Value(lvalue.clone()), one);
+    let value = compile_value_as(value, &ty, compiler);
+    statements.push(Operand::SetValue(compile_astvalue(lvalue, compiler), value));
+
+    (statements, result)
+}
+
+/// Registers given to `r` and `m` operands, in the order they are handed out. Callee saved
+/// registers are left out, so only a clobber can make them need saving
+const ASM_REGISTERS: [&str; 8] = ["rcx", "rdx", "rsi", "rdi", "r8", "r9", "r10", "r11"];
+const CALLEE_SAVED_REGISTERS: [&str; 5] = ["rbx", "r12", "r13", "r14", "r15"];
+
+/// The name of the low `bytes` of a 64 bit register, e.g. `ecx` for the low 4 bytes of `rcx`
+fn sized_register(register: &str, bytes: u32) -> String {
+    if let Some(number) = register.strip_prefix('r').filter(|n| n.parse::<u8>().is_ok()) {
+        return match bytes {
+            1 => format!("r{number}b"),
+            2 => format!("r{number}w"),
+            4 => format!("r{number}d"),
+            _ => register.to_string(),
+        };
+    }
+
+    let name = &register[1..];
+    match bytes {
+        1 if name.ends_with('x') => format!("{}l", &name[..1]),
+        1 => format!("{name}l"),
+        2 => name.to_string(),
+        4 => format!("e{name}"),
+        _ => register.to_string(),
+    }
+}
+
+/// The NASM size keyword for a memory operand
+fn memory_size(bytes: u32) -> &'static str {
+    match bytes {
+        1 => "byte",
+        2 => "word",
+        4 => "dword",
+        _ => "qword",
+    }
+}
+
+/// Compiles GCC style extended assembly. Register operands are loaded from temporaries right
+/// before the template and outputs are stored right after it, so nothing else runs in between
+fn compile_asm(
+    template: String,
+    outputs: Vec<(String, ASTNode)>,
+    inputs: Vec<(String, ASTNode)>,
+    clobbers: Vec<String>,
+    compiler: &mut CompileState,
+) -> Vec<Operand> {
+    let mut registers = ASM_REGISTERS
+        .iter()
+        .filter(|register| !clobbers.iter().any(|clobber| clobber == *register))
+        .map(|register| register.to_string())
+        .collect::<Vec<String>>()
+        .into_iter();
+    let mut allocate = || {
+        registers.next().unwrap_or_else(|| {
+            eprintln!("Error: Not enough registers for the operands of asm");
+            panic!()
+        })
+    };
+
+    // Everything is evaluated before the registers are loaded
+    let mut statements = vec![];
+    let mut loads = vec![];
+    let mut stores = vec![];
+    let mut assignments = vec![];
+    let mut operands = vec![];
+
+    let output_count = outputs.len();
+    let is_output = |i: usize| i < output_count;
+    for (i, (constraint, value)) in outputs.into_iter().chain(inputs).enumerate() {
+        let (modifier, kind) = match constraint.strip_prefix(['=', '+']) {
+            Some(kind) => (&constraint[..1], kind),
+            None => ("", constraint.as_str()),
+        };
+
+        if is_output(i) == modifier.is_empty() {
+            eprintln!("Error: Invalid constraint \"{constraint}\" for asm operand {i}");
+            panic!()
+        }
+
+        match kind {
+            "i" => match fold::evaluate(&value) {
+                Some(constant) => operands.push(constant.value.to_string()),
+                None => {
+                    eprintln!("Error: asm operand {i} has an \"i\" constraint but isn't constant");
+                    panic!()
+                }
+            },
+            "m" => {
+                let (mut reduced, lvalue) = compile_lvalue(value, compiler);
+                statements.append(&mut reduced);
+                if is_output(i) {
+                    check_writable(&lvalue, compiler);
+                }
+
+                let ty = compiler.object_type(&ASTNode::Value(lvalue.clone()));
+                let address = match lvalue {
+                    ASTValue::Deref(pointer) => Value::Variable(compiler.resolve(&pointer)),
+                    ASTValue::StringLiteral(name) => Value::Reference(compiler.resolve(&name)),
+                    _ => unreachable!(),
+                };
+                let pointer_type = Type::PTR(Box::new(ty.clone()));
+                let pointer = compiler.temporary(pointer_type.clone());
+                statements.push(Operand::DeclareVariable(pointer_type.into_ir(), pointer.clone(), address));
+
+                let register = allocate();
+                loads.push(Operand::SetRegister(register.clone(), Value::Variable(pointer)));
+                operands.push(format!("{} [{register}]", memory_size(ty.bytes())));
+            }
+            "r" if is_output(i) => {
+                let (mut reduced, lvalue) = compile_lvalue(value, compiler);
+                statements.append(&mut reduced);
+                check_writable(&lvalue, compiler);
+
+                let ty = compiler.object_type(&ASTNode::Value(lvalue.clone())).unqualified().clone();
+                if !ty.is_integer() && !ty.is_pointer() {
+                    eprintln!("Error: asm operand {i} of type {ty:?} can't be held in a register");
+                    panic!()
+                }
+
+                let register = allocate();
+                if modifier == "+" {
+                    let current = compile_astvalue(lvalue.clone(), compiler);
+                    let name = compiler.temporary(ty.clone());
+                    statements.push(Operand::DeclareVariable(ty.into_ir(), name.clone(), current));
+                    loads.push(Operand::SetRegister(register.clone(), Value::Variable(name)));
+                }
+
+                let result = compiler.temporary(ty.clone());
+                statements.push(Operand::DeclareVariable(ty.into_ir(), result.clone(), Value::Null));
+                stores.push(Operand::SetValue(
+                    Value::Variable(result.clone()),
+                    Value::Register(register.clone()),
+                ));
+                assignments.push(Operand::SetValue(
+                    compile_astvalue(lvalue, compiler),
+                    Value::Variable(result),
+                ));
+                operands.push(sized_register(&register, ty.bytes()));
+            }
+            "r" => {
+                let ty = compiler.type_of(&value);
+                if !ty.is_integer() && !ty.is_pointer() {
+                    eprintln!("Error: asm operand {i} of type {ty:?} can't be held in a register");
+                    panic!()
+                }
+
+                let value = compile_value(value, compiler);
+                let name = compiler.temporary(ty.clone());
+                statements.push(Operand::DeclareVariable(ty.into_ir(), name.clone(), value));
+
+                let register = allocate();
+                loads.push(Operand::SetRegister(register.clone(), Value::Variable(name)));
+                operands.push(sized_register(&register, ty.bytes()));
+            }
+            _ => {
+                eprintln!("Error: Unsupported asm constraint \"{constraint}\", only r, m and i are");
+                panic!()
+            }
+        }
+    }
+
+    // %N is replaced by operand N and %% by a single %, everything else is kept verbatim
+    let template = String::from_utf8_lossy(&string_bytes(&template)).into_owned();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            text.push('%');
+            continue;
+        }
+
+        let mut index = String::new();
+        while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+            index.push(digit);
+        }
+        match index.parse::<usize>().ok().and_then(|i| operands.get(i)) {
+            Some(operand) => text.push_str(operand),
+            None => {
+                eprintln!("Error: Invalid operand reference %{index} in asm");
+                panic!()
+            }
+        }
+    }
+
+    // Clobbered callee saved registers still have to be preserved for our caller
+    let saved = CALLEE_SAVED_REGISTERS
+        .iter()
+        .filter(|register| clobbers.iter().any(|clobber| clobber == *register))
+        .collect::<Vec<_>>();
+    for register in &saved {
+        text = format!("push {register}\n{text}");
+    }
+    for register in &saved {
+        text = format!("{text}\npop {register}");
+    }
+
+    statements.append(&mut loads);
+    statements.push(Operand::InlineAssembly(text));
+    statements.append(&mut stores);
+    statements.append(&mut assignments);
+    statements
+}
+
+/// Runs `main_body` if `predicate` holds and `other_body` otherwise, the IR has no else so the
+/// predicate is evaluated once into a temporary
+fn compile_branches(
+    predicate: ASTNode,
+    main_body: Vec<Operand>,
+    other_body: Vec<Operand>,
+    compiler: &mut CompileState,
+) -> Vec<Operand> {
+    let condition = Value::Compare(Box::new(compile_condition(predicate, compiler)));
+    let name = compiler.temporary(Type::INT);
+
+    vec![
+        Operand::DeclareVariable(Type::INT.into_ir(), name.clone(), condition),
+        Operand::If {
+            predicate: ComparePredicate {
+                operation: CompareOperation::NEQ,
+                lhs: Value::Variable(name.clone()),
+                rhs: Value::Int("0".to_string()),
+            },
+            main_body,
+        },
+        Operand::If {
+            predicate: ComparePredicate {
+                operation: CompareOperation::EQ,
+                lhs: Value::Variable(name),
+                rhs: Value::Int("0".to_string()),
+            },
+            main_body: other_body,
+        },
+    ]
+}
+
+/// Compiles an explicit cast, which may convert between any two scalar types
+fn compile_cast(ty: Type, value: ASTNode, compiler: &mut CompileState) -> Value {
+    let from = compiler.type_of(&value);
+
+    // Casting to void evaluates the value for its side effects only
+    if ty == Type::VOID {
+        let statements = compile_node(value, compiler);
+        return Value::Sequence(statements, Box::new(Value::Null));
+    }
+
+    let is_scalar = |ty: &Type| ty.is_arithmetic() || ty.is_pointer();
+    if !is_scalar(&ty) || !is_scalar(&from) {
+        eprintln!("Error: Invalid cast from {from:?} to {ty:?}, both types must be scalar");
+        panic!()
+    }
+
+    if (from.is_pointer() && ty.is_floating()) || (from.is_floating() && ty.is_pointer()) {
+        eprintln!("Error: Invalid cast from {from:?} to {ty:?}");
+        panic!()
+    }
+
+    match (from.is_pointer(), ty.is_pointer()) {
+        (false, false) => compile_value_as(value, &ty, compiler),
+        (true, true) => compile_value(value, compiler),
+        // Like any scalar, a pointer converts to _Bool by comparing it with 0
+        (true, false) if *ty.unqualified() == Type::BOOL => compile_value_as(value, &ty, compiler),
+        // Pointers are converted to and from integers as unsigned 64 bit values
+        (true, false) => {
+            let value = compile_value(value, compiler);
+            convert(value, &Type::ULONG, &ty)
+        }
+        (false, true) => {
+            let value = compile_value(value, compiler);
+            convert(value, &from, &Type::ULONG)
+        }
+    }
+}
+
+fn compile_value(value: ASTNode, compiler: &mut CompileState) -> Value {
+    // Integer constant expressions are folded, the callers have already taken their type
+    if !matches!(value, ASTNode::Value(_)) {
+        if let Some(constant) = fold::evaluate(&value) {
+            return Value::Int(constant.value.to_string());
+        }
+    }
+
+    match value {
+        ASTNode::Add(lhs, rhs) if compiler.type_of(&lhs).is_pointer() || compiler.type_of(&rhs).is_pointer() => {
+            compile_pointer_arithmetic(*lhs, *rhs, false, compiler)
+        }
+        ASTNode::Sub(lhs, rhs) if compiler.type_of(&lhs).is_pointer() || compiler.type_of(&rhs).is_pointer() => {
+            compile_pointer_arithmetic(*lhs, *rhs, true, compiler)
+        }
+        ASTNode::Add(lhs, rhs) => match compile_arithmetic(*lhs, *rhs, compiler) {
+            (ty, lhs, rhs) if ty.is_floating() => Value::FloatAdd(lhs, rhs),
+            (_, lhs, rhs) => Value::Add(lhs, rhs),
+        },
+        ASTNode::Sub(lhs, rhs) => match compile_arithmetic(*lhs, *rhs, compiler) {
+            (ty, lhs, rhs) if ty.is_floating() => Value::FloatSub(lhs, rhs),
+            (_, lhs, rhs) => Value::Sub(lhs, rhs),
+        },
+        ASTNode::Mul(lhs, rhs) => match compile_arithmetic(*lhs, *rhs, compiler) {
+            (ty, lhs, rhs) if ty.is_floating() => Value::FloatMul(lhs, rhs),
+            (_, lhs, rhs) => Value::Mul(lhs, rhs),
+        },
+        ASTNode::Div(lhs, rhs) => match compile_arithmetic(*lhs, *rhs, compiler) {
+            (ty, lhs, rhs) if ty.is_floating() => Value::FloatDiv(lhs, rhs),
+            (ty, lhs, rhs) if ty.is_unsigned() => Value::UnsignedDiv(lhs, rhs),
+            (_, lhs, rhs) => Value::Div(lhs, rhs),
+        },
+        ASTNode::Mod(lhs, rhs) => match compile_arithmetic(*lhs, *rhs, compiler) {
+            (ty, lhs, rhs) if ty.is_unsigned() => Value::UnsignedMod(lhs, rhs),
+            (_, lhs, rhs) => Value::Mod(lhs, rhs),
+        },
+        ASTNode::BitAnd(lhs, rhs) => {
+            let (_, lhs, rhs) = compile_bitwise(*lhs, *rhs, compiler);
+            Value::BitAnd(lhs, rhs)
+        }
+        ASTNode::BitOr(lhs, rhs) => {
+            let (_, lhs, rhs) = compile_bitwise(*lhs, *rhs, compiler);
+            Value::BitOr(lhs, rhs)
+        }
+        ASTNode::BitXor(lhs, rhs) => {
+            let (_, lhs, rhs) = compile_bitwise(*lhs, *rhs, compiler);
+            Value::BitXor(lhs, rhs)
+        }
+        ASTNode::BitNot(value) => {
+            let ty = compiler.integer_type_of(&value).promote();
+            Value::BitNot(Box::new(compile_value_as(*value, &ty, compiler)))
+        }
+        ASTNode::ShiftLeft(lhs, rhs) => {
+            let (_, lhs, rhs) = compile_shift(*lhs, *rhs, compiler);
+            Value::ShiftLeft(lhs, rhs)
+        }
+        // Right shifts of signed values are arithmetic (sar), of unsigned values logical (shr)
+        ASTNode::ShiftRight(lhs, rhs) => match compile_shift(*lhs, *rhs, compiler) {
+            (ty, lhs, rhs) if ty.is_unsigned() => Value::UnsignedShiftRight(lhs, rhs),
+            (_, lhs, rhs) => Value::ShiftRight(lhs, rhs),
+        },
+        ASTNode::Compare(predicate) => {
+            Value::Compare(Box::new(compile_comparison(predicate, compiler)))
+        }
+        // Both operands are normalised to 0 or 1, the IR only evaluates `rhs` when needed
+        ASTNode::And(lhs, rhs) => Value::LogicalAnd(
+            Box::new(Value::Compare(Box::new(compile_condition(*lhs, compiler)))),
+            Box::new(Value::Compare(Box::new(compile_condition(*rhs, compiler)))),
+        ),
+        ASTNode::Or(lhs, rhs) => Value::LogicalOr(
+            Box::new(Value::Compare(Box::new(compile_condition(*lhs, compiler)))),
+            Box::new(Value::Compare(Box::new(compile_condition(*rhs, compiler)))),
+        ),
+        ASTNode::Not(value) => {
+            let zero = ASTNode::Value(ASTValue::IntValue(0));
+            Value::Compare(Box::new(compile_comparison(
+                Comparison {
+                    lhs: value,
+                    rhs: Box::new(zero),
+                    operation: CompareOperation::EQ,
+                },
+                compiler,
+            )))
+        }
+        // Expressions with side effects run their operands in place before yielding a value
+        ASTNode::Deref(pointer) => {
+            let (statements, lvalue) = compile_lvalue(ASTNode::Deref(pointer), compiler);
+            Value::Sequence(statements, Box::new(compile_astvalue(lvalue, compiler)))
+        }
+        ASTNode::Increment {
+            lvalue,
+            decrement,
+            postfix,
+        } => {
+            let (statements, value) = compile_increment(*lvalue, decrement, postfix, compiler);
+            Value::Sequence(statements, Box::new(value))
+        }
+        // An assignment yields the value of its lvalue after the assignment
+        ASTNode::SetVariable(lvalue, value) => {
+            let (statements, lvalue) = compile_assignment(*lvalue, *value, compiler);
+            Value::Sequence(statements, Box::new(compile_astvalue(lvalue, compiler)))
+        }
+        ASTNode::CompoundAssignment(lvalue, operation, value) => {
+            let (statements, lvalue) =
+                compile_compound_assignment(*lvalue, &operation, *value, compiler);
+            Value::Sequence(statements, Box::new(compile_astvalue(lvalue, compiler)))
+        }
+        ASTNode::Ternary(predicate, main, other) => {
+            let ty = compiler.type_of(&ASTNode::Ternary(
+                predicate.clone(),
+                main.clone(),
+                other.clone(),
+            ));
+
+            if ty == Type::VOID {
+                let statements = compile_node(ASTNode::Ternary(predicate, main, other), compiler);
+                return Value::Sequence(statements, Box::new(Value::Null));
+            }
+
+            let name = compiler.temporary(ty.clone());
+            let main = compile_value_as(*main, &ty, compiler);
+            let other = compile_value_as(*other, &ty, compiler);
+
+            let mut statements = vec![Operand::DeclareVariable(ty.into_ir(), name.clone(), Value::Null)];
+            statements.append(&mut compile_branches(
+                *predicate,
+                vec![Operand::SetValue(Value::Variable(name.clone()), main)],
+                vec![Operand::SetValue(Value::Variable(name.clone()), other)],
+                compiler,
+            ));
+            Value::Sequence(statements, Box::new(Value::Variable(name)))
+        }
+        ASTNode::Cast(ty, value) => compile_cast(ty, *value, compiler),
+        ASTNode::VaArg(list, ty) => {
+            let (statements, value) = compile_va_arg(*list, ty, compiler);
+            Value::Sequence(statements, Box::new(value))
+        }
+        ASTNode::SizeOf(value) => Value::Int(compiler.object_type(&value).bytes().to_string()),
+        ASTNode::SizeOfType(ty) => Value::Int(ty.bytes().to_string()),
+        ASTNode::AlignOfType(ty) => Value::Int(ty.align().to_string()),
+        ASTNode::Comma(lhs, rhs) => {
+            let statements = compile_node(*lhs, compiler);
+            Value::Sequence(statements, Box::new(compile_value(*rhs, compiler)))
+        }
+        ASTNode::Value(value) => compile_astvalue(value, compiler),
+        value => {
+            eprintln!("Expected a value; Recieved {value:#?} instead");
+            panic!()
+        }
+    }
+}
+
+/// Comparisons of unsigned integers and pointers use the unsigned condition codes
+fn compile_comparison(predicate: Comparison, compiler: &mut CompileState) -> ComparePredicate {
+    let (ty, lhs, rhs) = compile_arithmetic(*predicate.lhs, *predicate.rhs, compiler);
+
+    if ty.is_floating() {
+        return compile_float_comparison(&ty, predicate.operation, *lhs, *rhs, compiler);
+    }
+
+    let operation = if ty.is_unsigned() {
+        match predicate.operation {
+            CompareOperation::GT => CompareOperation::UGT,
+            CompareOperation::LT => CompareOperation::ULT,
+            CompareOperation::GTE => CompareOperation::UGTE,
+            CompareOperation::LTE => CompareOperation::ULTE,
+            operation => operation,
+        }
+    } else {
+        predicate.operation
+    };
+
+    ComparePredicate {
+        operation,
+        lhs: *lhs,
+        rhs: *rhs,
+    }
+}
+
+/// `ucomiss` and `ucomisd` set the flags like an unsigned compare, and report a NaN operand by
+/// setting ZF, PF and CF together. Every comparison but `!=` has to be false then, and `ja` and
+/// `jae` are the only conditions that are, so `<` and `<=` swap their operands like GCC does and
+/// `==` is built from `>=` both ways round
+fn compile_float_comparison(
+    ty: &Type,
+    operation: CompareOperation,
+    lhs: Value,
+    rhs: Value,
+    compiler: &mut CompileState,
+) -> ComparePredicate {
+    let compare = |operation, lhs, rhs| ComparePredicate { operation, lhs, rhs };
+
+    let equal_operation = match operation {
+        CompareOperation::GT => return compare(CompareOperation::UGT, lhs, rhs),
+        CompareOperation::GTE => return compare(CompareOperation::UGTE, lhs, rhs),
+        CompareOperation::LT => return compare(CompareOperation::UGT, rhs, lhs),
+        CompareOperation::LTE => return compare(CompareOperation::UGTE, rhs, lhs),
+        // Whether the operands are equal is tested against 0 the right way round
+        CompareOperation::EQ => CompareOperation::NEQ,
+        CompareOperation::NEQ => CompareOperation::EQ,
+        _ => unreachable!(),
+    };
+
+    let lhs_name = compiler.temporary(ty.clone());
+    let rhs_name = compiler.temporary(ty.clone());
+    let at_least = |lhs: &str, rhs: &str| {
+        Box::new(Value::Compare(Box::new(compare(
+            CompareOperation::UGTE,
+            Value::Variable(lhs.to_string()),
+            Value::Variable(rhs.to_string()),
+        ))))
+    };
+
+    let equal = Value::Sequence(
+        vec![
+            Operand::DeclareVariable(ty.into_ir(), lhs_name.clone(), lhs),
+            Operand::DeclareVariable(ty.into_ir(), rhs_name.clone(), rhs),
+        ],
+        Box::new(Value::LogicalAnd(at_least(&lhs_name, &rhs_name), at_least(&rhs_name, &lhs_name))),
+    );
+    compare(equal_operation, equal, Value::Int("0".to_string()))
+}
+
+/// Compiles a controlling expression, anything that isn't a comparison is tested against 0
+fn compile_condition(condition: ASTNode, compiler: &mut CompileState) -> ComparePredicate {
+    match condition {
+        ASTNode::Compare(predicate) => compile_comparison(predicate, compiler),
+        condition => {
+            let zero = ASTNode::Value(ASTValue::IntValue(0));
+            compile_comparison(
+                Comparison {
+                    lhs: Box::new(condition),
+                    rhs: Box::new(zero),
+                    operation: CompareOperation::NEQ,
+                },
+                compiler,
+            )
+        }
+    }
+}
+
+/// Compiles the callee of an indirect call, `(*fp)` and `fp` both being the pointer itself
+fn compile_callee(callee: ASTNode, compiler: &mut CompileState) -> Value {
+    match callee {
+        ASTNode::Value(ASTValue::Deref(name)) | ASTNode::Value(ASTValue::StringLiteral(name)) => {
+            compile_astvalue(ASTValue::StringLiteral(name), compiler)
+        }
+        callee => compile_value(callee, compiler),
+    }
+}
+
+/// The parameter types of a function or function pointer and whether it is variadic
+fn signature(callee: Option<Type>) -> (Vec<Type>, bool) {
+    match callee.map(|ty| ty.unqualified().clone()) {
+        Some(Type::FUNC(_, parameters, variadic)) => (parameters, variadic),
+        Some(Type::PTR(inner)) if inner.is_function() => signature(Some(*inner)),
+        _ => (vec![], false),
+    }
+}
+
+/// Compiles call arguments, converting each to the type of its parameter. Arguments without a
+/// parameter get the default argument promotions, `float` to `double` and integers to `int`
+fn compile_arguments(parameters: &[Type], values: Vec<ASTNode>, compiler: &mut CompileState) -> Vec<(Type, Value)> {
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let ty = match parameters.get(i) {
+                Some(ty) => ty.clone(),
+                None => {
+                    let ty = compiler.type_of(&v);
+                    if ty.is_floating() {
+                        Type::DOUBLE
+                    } else if ty.is_integer() {
+                        ty.promote()
+                    } else {
+                        ty
+                    }
+                }
+            };
+            let value = compile_value_as(v, &ty, compiler);
+            (ty, value)
+        })
+        .collect()
+}
+
+/// The name of the vector move for a floating point value of type `ty`
+fn float_move(ty: &Type) -> &'static str {
+    if *ty.unqualified() == Type::FLOAT {
+        "movss"
+    } else {
+        "movsd"
+    }
+}
+
+/// Compiles a call to `target`, either a `FunctionReference` or a function pointer, following the
+/// System V ABI. The arguments are evaluated into temporaries first, then `rsp` is aligned down to
+/// 16 bytes with its old value saved above the stack arguments, which are pushed right to left.
+/// Only caller saved registers are loaded, `rax` last to hold the vector register count of a
+/// variadic call
+fn compile_call_with(
+    callee: Option<Type>,
+    return_type: Type,
+    target: Value,
+    values: Vec<ASTNode>,
+    compiler: &mut CompileState,
+) -> Value {
+    let (parameters, variadic) = signature(callee);
+    let arguments = compile_arguments(&parameters, values, compiler);
+
+    let types = arguments.iter().map(|(ty, _)| ty.clone()).collect::<Vec<Type>>();
+    let (locations, usage) = abi::locations(&types);
+
+    let mut statements = vec![];
+    let mut names = vec![];
+    for (ty, value) in arguments {
+        // Arguments narrower than int are extended to it, as GCC and Clang expect
+        let wide = if ty.is_integer() { ty.promote() } else { ty.clone() };
+        let name = compiler.temporary(wide.clone());
+        statements.push(Operand::DeclareVariable(wide.into_ir(), name.clone(), convert(value, &ty, &wide)));
+        names.push((wide, name));
+    }
+
+    let mut loads = vec![];
+    let instruction = match target {
+        Value::FunctionReference(name) => {
+            statements.append(&mut compiler.declare_extern(&name));
+            format!("call {name}")
+        }
+        pointer => {
+            let ty = Type::PTR(Box::new(Type::VOID));
+            let name = compiler.temporary(ty.clone());
+            statements.push(Operand::DeclareVariable(ty.into_ir(), name.clone(), pointer));
+            loads.push(Operand::SetRegister("r10".to_string(), Value::Variable(name)));
+            "call r10".to_string()
+        }
+    };
+
+    let result = compiler.temporary(return_type.clone());
+    if *return_type.unqualified() != Type::VOID {
+        statements.push(Operand::DeclareVariable(return_type.into_ir(), result.clone(), Value::Null));
+    }
+
+    // After the old rsp is pushed an odd number of eightbytes keeps the call aligned
+    let padding = if usage.stack % 16 == 0 { 8 } else { 0 };
+    let mut align = "mov rax, rsp\nand rsp, -16\npush rax".to_string();
+    if padding != 0 {
+        align.push_str(&format!("\nsub rsp, {padding}"));
+    }
+    statements.push(Operand::InlineAssembly(align));
+
+    for ((_, name), location) in names.iter().zip(&locations).rev() {
+        if let abi::Location::Stack(_) = location {
+            statements.push(Operand::SetRegister("r11".to_string(), Value::Reference(name.clone())));
+            statements.push(Operand::InlineAssembly("push qword [r11]".to_string()));
+        }
+    }
+
+    for ((ty, name), location) in names.iter().zip(&locations) {
+        match location {
+            abi::Location::Sse(register) => {
+                statements.push(Operand::SetRegister("r11".to_string(), Value::Reference(name.clone())));
+                statements.push(Operand::InlineAssembly(format!("{} {register}, [r11]", float_move(ty))));
+            }
+            abi::Location::Integer(register) => {
+                statements.push(Operand::SetRegister(register.to_string(), Value::Variable(name.clone())));
+            }
+            abi::Location::Stack(_) => {}
+        }
+    }
+
+    statements.append(&mut loads);
+    if variadic {
+        statements.push(Operand::SetRegister("rax".to_string(), Value::Int(usage.sse.to_string())));
+    }
+    statements.push(Operand::InlineAssembly(format!(
+        "{instruction}\nadd rsp, {}\npop rsp",
+        usage.stack + padding
+    )));
+
+    if return_type.is_floating() {
+        statements.push(Operand::SetRegister("r11".to_string(), Value::Reference(result.clone())));
+        statements.push(Operand::InlineAssembly(format!("{} [r11], xmm0", float_move(&return_type))));
+    } else if *return_type.unqualified() != Type::VOID {
+        statements.push(Operand::SetValue(
+            Value::Variable(result.clone()),
+            Value::Register("rax".to_string()),
+        ));
+    }
+
+    let value = match *return_type.unqualified() {
+        Type::VOID => Value::Null,
+        _ => Value::Variable(result),
+    };
+    Value::Sequence(statements, Box::new(value))
+}
+
+/// The number of arguments after the system call number taken by `__builtin_syscall0` to
+/// `__builtin_syscall6`
+fn syscall_builtin(name: &str) -> Option<usize> {
+    name.strip_prefix("__builtin_syscall")?
+        .parse::<usize>()
+        .ok()
+        .filter(|count| *count <= abi::SYSCALL_REGISTERS.len())
+}
+
+/// Compiles a system call builtin, returning the operands and the value left in `rax`. The
+/// arguments are all evaluated before any register is loaded
+fn compile_syscall(count: usize, values: Vec<ASTNode>, compiler: &mut CompileState) -> (Vec<Operand>, Value) {
+    if values.len() != count + 1 {
+        eprintln!(
+            "Error: __builtin_syscall{count} takes a system call number and {count} arguments, got {} values",
+            values.len()
         );
+        panic!()
+    }
+
+    let mut statements = vec![];
+    let mut loads = vec![];
+    let registers = ["rax"].into_iter().chain(abi::SYSCALL_REGISTERS);
+    for (value, register) in values.into_iter().zip(registers) {
+        let value = compile_value_as(value, &Type::LONG, compiler);
+        let name = compiler.temporary(Type::LONG);
+        statements.push(Operand::DeclareVariable(Type::LONG.into_ir(), name.clone(), value));
+        loads.push(Operand::SetRegister(register.to_string(), Value::Variable(name)));
     }
 
-    if let ASTNode::Value(value) = value {
-        compile_astvalue(value, compiler)
+    let result = compiler.temporary(Type::LONG);
+    statements.push(Operand::DeclareVariable(Type::LONG.into_ir(), result.clone(), Value::Null));
+    // rax is loaded last, as loading the other registers may go through it
+    loads.rotate_left(1);
+    statements.append(&mut loads);
+    // The kernel clobbers rcx and r11, which never hold anything across statements
+    statements.push(Operand::InlineAssembly("syscall".to_string()));
+    statements.push(Operand::SetValue(
+        Value::Variable(result.clone()),
+        Value::Register("rax".to_string()),
+    ));
+
+    (statements, Value::Variable(result))
+}
+
+fn compile_call(name: String, values: Vec<ASTNode>, compiler: &mut CompileState) -> Value {
+    if let Some(count) = syscall_builtin(&name).filter(|_| compiler.lookup(&name).is_none()) {
+        let (statements, value) = compile_syscall(count, values, compiler);
+        return Value::Sequence(statements, Box::new(value));
+    }
+
+    let callee = compiler.lookup(&name).cloned();
+    // Calls to undeclared functions are implicitly declared as returning int
+    let return_type = callee.as_ref().map_or(Type::INT, |ty| compiler.call_type(ty));
+
+    let target = if callee.as_ref().is_some_and(|ty| ty.is_function_pointer()) {
+        Value::Variable(compiler.resolve(&name))
     } else {
-        eprintln!("Expected a value; Recieved {value:#?} instead");
+        Value::FunctionReference(name)
+    };
+    compile_call_with(callee, return_type, target, values, compiler)
+}
+
+fn compile_indirect_call(callee: ASTNode, values: Vec<ASTNode>, compiler: &mut CompileState) -> Value {
+    let callee_type = compiler.type_of(&callee);
+    let return_type = compiler.call_type(&callee_type);
+    let pointer = compile_callee(callee, compiler);
+    compile_call_with(Some(callee_type), return_type, pointer, values, compiler)
+}
+
+/// Sets up a `va_list` to the first variable argument, see `CompileState::va_offsets`
+fn compile_va_start(list: ASTNode, compiler: &mut CompileState) -> Vec<Operand> {
+    let Some((gp_offset, fp_offset, stack_offset)) = compiler.va_offsets else {
+        eprintln!("Error: va_start used in a function with fixed arguments");
+        panic!()
+    };
+
+    let list_type = Type::PTR(Box::new(Type::ULONG));
+    let list_value = compile_value_as(list, &list_type, compiler);
+    let list = compiler.temporary(list_type.clone());
+    let overflow_area = compiler.temporary(list_type.clone());
+    let reg_save_area = compiler.temporary(list_type.clone());
+
+    vec![
+        Operand::DeclareVariable(list_type.into_ir(), list.clone(), list_value),
+        Operand::SetValue(
+            Value::Dereference(list.clone()),
+            Value::Int((gp_offset | fp_offset << 32).to_string()),
+        ),
+        Operand::DeclareVariable(
+            list_type.into_ir(),
+            overflow_area.clone(),
+            Value::Add(Box::new(Value::Variable(list.clone())), Box::new(Value::Int("8".to_string()))),
+        ),
+        Operand::SetValue(
+            Value::Dereference(overflow_area),
+            Value::Add(
+                Box::new(Value::StackArguments),
+                Box::new(Value::Int(stack_offset.to_string())),
+            ),
+        ),
+        Operand::DeclareVariable(
+            list_type.into_ir(),
+            reg_save_area.clone(),
+            Value::Add(Box::new(Value::Variable(list)), Box::new(Value::Int("16".to_string()))),
+        ),
+        Operand::SetValue(
+            Value::Dereference(reg_save_area),
+            Value::Reference("_va_save".to_string()),
+        ),
+    ]
+}
+
+/// Compiles `va_arg`, taking the next argument from the register save area while registers of
+/// its class are left and from the stack after that
+fn compile_va_arg(list: ASTNode, ty: Type, compiler: &mut CompileState) -> (Vec<Operand>, Value) {
+    let ty = ty.unqualified().clone();
+    if !ty.is_arithmetic() && !ty.is_pointer() {
+        eprintln!("Error: va_arg of type {ty:?} is not supported");
         panic!()
     }
+
+    // Floating point arguments are counted by fp_offset, in the 16 byte vector registers that
+    // follow the 6 general purpose ones
+    let (offset, limit, step) = if ty.is_floating() { (4, 176, 16) } else { (0, 48, 8) };
+
+    let list_type = Type::PTR(Box::new(Type::ULONG));
+    let offset_type = Type::PTR(Box::new(Type::UINT));
+    let list_value = compile_value_as(list, &list_type, compiler);
+    let list = compiler.temporary(list_type.clone());
+    let register_offset = compiler.temporary(offset_type.clone());
+    let address = compiler.temporary(Type::ULONG);
+
+    let mut statements = vec![
+        Operand::DeclareVariable(list_type.into_ir(), list.clone(), list_value),
+        Operand::DeclareVariable(
+            offset_type.into_ir(),
+            register_offset.clone(),
+            Value::Add(
+                Box::new(Value::Variable(list.clone())),
+                Box::new(Value::Int(offset.to_string())),
+            ),
+        ),
+        Operand::DeclareVariable(Type::ULONG.into_ir(), address.clone(), Value::Null),
+    ];
+
+    let variable = |name: &str| Box::new(ASTNode::Value(ASTValue::StringLiteral(name.to_string())));
+    let int = |value: i64| Box::new(ASTNode::Value(ASTValue::IntValue(value)));
+    // The fields of the va_list, counted in unsigned longs as pointer arithmetic is scaled
+    let field = |index: i64| Box::new(ASTNode::Deref(Box::new(ASTNode::Add(variable(&list), int(index)))));
+    let current_offset = Box::new(ASTNode::Value(ASTValue::Deref(register_offset)));
+
+    let from_registers = compile_list(
+        vec![
+            ASTNode::SetVariable(variable(&address), Box::new(ASTNode::Add(field(2), current_offset.clone()))),
+            ASTNode::CompoundAssignment(current_offset.clone(), "+".to_string(), int(step)),
+        ],
+        compiler,
+    );
+    let from_stack = compile_list(
+        vec![
+            ASTNode::SetVariable(variable(&address), field(1)),
+            ASTNode::CompoundAssignment(field(1), "+".to_string(), int(8)),
+        ],
+        compiler,
+    );
+    let predicate = ASTNode::Compare(Comparison {
+        lhs: current_offset,
+        rhs: int(limit),
+        operation: CompareOperation::LT,
+    });
+    statements.append(&mut compile_branches(predicate, from_registers, from_stack, compiler));
+
+    let value_type = Type::PTR(Box::new(ty));
+    let value = compiler.temporary(value_type.clone());
+    statements.push(Operand::DeclareVariable(value_type.into_ir(), value.clone(), Value::Variable(address)));
+
+    (statements, Value::Dereference(value))
 }
 
-pub fn compile_astvalue(value: ASTValue, compiler: &mut Compiler) -> Value {
+pub fn compile_astvalue(value: ASTValue, compiler: &mut CompileState) -> Value {
     match value {
         ASTValue::IntValue(value) => Value::Int(value.to_string()),
-        ASTValue::StringLiteral(value) => Value::Variable(value),
-        ASTValue::FunctionCall(name, values) => Value::FunctionCall(
-            name,
-            values
-                .iter()
-                .cloned()
-                .map(|v| compile_value(v, compiler))
-                .collect(),
-        ),
-        ASTValue::Deref(name) => Value::Dereference(name),
-        ASTValue::Ref(name) => Value::Reference(name),
-        ASTValue::CharValue(value) => Value::Char(value),
-        ASTValue::StringValue(value) => {
-            let define_name = format!("_SD{}", compiler.string_defines.len());
-            compiler.string_defines.push((define_name.clone(), value));
-            Value::StringLiteral(define_name)
+        ASTValue::FloatValue(value) => compile_float(value, &Type::DOUBLE, compiler),
+        // A function designator decays into a pointer to the function
+        ASTValue::StringLiteral(value) | ASTValue::Ref(value) if compiler.is_function(&value) => {
+            Value::FunctionReference(value)
+        }
+        // An array decays into a pointer to its first element
+        ASTValue::StringLiteral(value)
+            if matches!(compiler.lookup(&value), Some(Type::ARRAY(_, _))) =>
+        {
+            Value::Reference(compiler.resolve(&value))
         }
+        ASTValue::StringLiteral(value) => Value::Variable(compiler.resolve(&value)),
+        ASTValue::FunctionCall(name, values) => compile_call(name, values, compiler),
+        ASTValue::IndirectCall(callee, values) => compile_indirect_call(*callee, values, compiler),
+        ASTValue::Deref(name) if matches!(compiler.lookup(&name), Some(Type::ARRAY(_, _))) => {
+            let (statements, lvalue) = compile_lvalue(ASTNode::Value(ASTValue::Deref(name)), compiler);
+            Value::Sequence(statements, Box::new(compile_astvalue(lvalue, compiler)))
+        }
+        ASTValue::Deref(name) => Value::Dereference(compiler.resolve(&name)),
+        ASTValue::Ref(name) => Value::Reference(compiler.resolve(&name)),
+        // Written as their value, as NASM character constants can't hold escapes such as '\n'
+        ASTValue::CharValue(value) => {
+            Value::Int(fold::Constant::new(value as i64, Type::CHAR).value.to_string())
+        }
+        ASTValue::StringValue(value) => Value::StringLiteral(compiler.intern_string(value)),
     }
 }
 
-fn compile_node(node: ASTNode, compiler: &mut Compiler) -> Vec<Operand> {
+fn compile_node(node: ASTNode, compiler: &mut CompileState) -> Vec<Operand> {
     let mut statements = vec![];
 
     match node {
@@ -59,56 +1564,157 @@ fn compile_node(node: ASTNode, compiler: &mut Compiler) -> Vec<Operand> {
             main_body,
             else_body,
         } => {
-            let lhs = compile_value(*predicate.lhs, compiler);
-            let rhs = compile_value(*predicate.rhs, compiler);
             statements.push(Operand::If {
-                predicate: ComparePredicate {
-                    operation: predicate.operation,
-                    lhs,
-                    rhs,
-                },
+                predicate: compile_condition(*predicate, compiler),
                 main_body: compile_list(main_body, compiler),
             });
         }
         ASTNode::SetVariable(lhs, value) => {
-            statements.push(Operand::SetValue(
-                compile_astvalue(lhs, compiler),
-                compile_value(*value, compiler),
-            ));
+            let (mut operands, _) = compile_assignment(*lhs, *value, compiler);
+            statements.append(&mut operands);
+        }
+        ASTNode::CompoundAssignment(lhs, operation, value) => {
+            let (mut operands, _) =
+                compile_compound_assignment(*lhs, &operation, *value, compiler);
+            statements.append(&mut operands);
+        }
+        ASTNode::Increment {
+            lvalue,
+            decrement,
+            postfix: _,
+        } => {
+            // The value is discarded, so postfix and prefix are the same
+            let (mut operands, _) = compile_increment(*lvalue, decrement, false, compiler);
+            statements.append(&mut operands);
         }
+        // Calls are always a sequence of operands, whose value is discarded here
         ASTNode::FunctionCall(name, values) => {
-            statements.push(Operand::FunctionCall(
-                name,
-                values
-                    .iter()
-                    .cloned()
-                    .map(|v| compile_value(v, compiler))
-                    .collect(),
-            ));
+            if let Value::Sequence(mut operands, _) = compile_call(name, values, compiler) {
+                statements.append(&mut operands);
+            }
+        }
+        ASTNode::IndirectCall(callee, values) => {
+            if let Value::Sequence(mut operands, _) = compile_indirect_call(*callee, values, compiler) {
+                statements.append(&mut operands);
+            }
+        }
+        ASTNode::VaStart(list) => {
+            statements.append(&mut compile_va_start(*list, compiler));
+        }
+        // There is nothing to release, the list is only evaluated
+        ASTNode::VaEnd(list) => {
+            statements.append(&mut compile_node(*list, compiler));
         }
         ASTNode::InlineAssembly(assembly) => {
             statements.push(Operand::InlineAssembly(format!(
                 "{assembly} ; User Defined Inline Assembly"
             )));
         }
-        ASTNode::FunctionDeclaration(ty, name, inner, params) => {
-            statements.push(Operand::FunctionDecl(
-                ty.into_ir(),
-                name,
-                compile_list(inner, compiler),
-                params
-                    .iter()
-                    .cloned()
-                    .map(|v| (v.0, v.1.into_ir()))
-                    .collect(),
-            ));
+        ASTNode::ExtendedAssembly {
+            template,
+            outputs,
+            inputs,
+            clobbers,
+        } => {
+            statements.append(&mut compile_asm(template, outputs, inputs, clobbers, compiler));
         }
-        ASTNode::VariableDeclaration(ty, name, value) => {
-            statements.push(Operand::DeclareVariable(
-                ty.into_ir(),
-                name,
-                compile_value(*value, compiler),
-            ));
+        ASTNode::FunctionDeclaration(ty, name, inner, params, variadic, storage) => {
+            compiler.enter_function(&params);
+            compiler.return_type = ty.clone();
+            if compiler.options.debug_info {
+                compiler.debug.function(&name, &ty);
+            }
+
+            // Static functions have internal linkage, so aren't made visible to the linker
+            if storage != StorageClass::Static {
+                statements.push(Operand::InlineAssembly(format!("global {name}")));
+            }
+
+            // A variadic function spills every argument register into the 176 byte register
+            // save area on entry, where `va_arg` looks for the arguments that fit in registers
+            let mut body = vec![];
+            compiler.va_offsets = None;
+            if variadic {
+                let types = params.iter().map(|(_, ty)| ty.clone()).collect::<Vec<Type>>();
+                let usage = abi::usage(&types);
+                compiler.va_offsets = Some((
+                    usage.integer as i64 * 8,
+                    abi::INTEGER_REGISTERS.len() as i64 * 8 + usage.sse as i64 * 16,
+                    usage.stack,
+                ));
+
+                let save_area = Type::ARRAY(Box::new(Type::UCHAR), 176);
+                body.push(Operand::DeclareVariable(save_area.into_ir(), "_va_save".to_string(), Value::Null));
+                body.push(Operand::SaveArgumentRegisters("_va_save".to_string()));
+                compiler.variables.insert("_va_save".to_string(), save_area);
+            }
+
+            // Parameters passed on the stack are copied out of the caller's frame, the IR only
+            // being given those that arrive in registers
+            let types = params.iter().map(|(_, ty)| ty.clone()).collect::<Vec<Type>>();
+            let (locations, _) = abi::locations(&types);
+            let mut register_params = vec![];
+            for ((param, ty), location) in params.iter().cloned().zip(locations) {
+                let abi::Location::Stack(offset) = location else {
+                    register_params.push((param, ty.into_ir()));
+                    continue;
+                };
+
+                let pointer_type = Type::PTR(Box::new(ty.clone()));
+                let pointer = compiler.temporary(pointer_type.clone());
+                body.push(Operand::DeclareVariable(
+                    pointer_type.into_ir(),
+                    pointer.clone(),
+                    Value::Add(Box::new(Value::StackArguments), Box::new(Value::Int(offset.to_string()))),
+                ));
+                body.push(Operand::DeclareVariable(ty.into_ir(), param, Value::Dereference(pointer)));
+            }
+            for (param, ty) in &params {
+                body.append(&mut compiler.describe_local(param, ty, true));
+            }
+            body.append(&mut compile_list(inner, compiler));
+
+            statements.push(Operand::FunctionDecl(ty.into_ir(), name, body, register_params));
+            if compiler.options.debug_info {
+                statements.push(Operand::InlineAssembly(compiler.debug.end_function()));
+            }
+        }
+        ASTNode::FunctionPrototype(_, name, _) => {
+            statements.append(&mut compiler.declare_extern(&name));
+        }
+        // Every following line of assembly belongs to this source line, until the next marker
+        ASTNode::SourceLine(file, line) => {
+            if compiler.options.annotate_asm {
+                statements.append(&mut compiler.annotate(&file, line));
+            }
+            if compiler.options.debug_info {
+                statements.extend(compiler.debug.line(&file, line).map(Operand::InlineAssembly));
+            }
+        }
+        // Static locals are stored at file scope under a unique label
+        ASTNode::VariableDeclaration(ty, name, value, StorageClass::Static) => {
+            let label = format!("_S{}_{name}", compiler.temporaries);
+            compiler.temporaries += 1;
+            if compiler.options.debug_info {
+                compiler.debug.variable(&name, &ty, dwarf::Location::Static(label.clone()), false);
+            }
+            compiler.statics.insert(name, label.clone());
+
+            let mut operands = compile_global(ty, label, value, StorageClass::Static, compiler);
+            compiler.file_operands.append(&mut operands);
+        }
+        ASTNode::VariableDeclaration(ty, name, value, StorageClass::Extern) => {
+            let mut operands = compile_global(ty, name, value, StorageClass::Extern, compiler);
+            compiler.file_operands.append(&mut operands);
+        }
+        ASTNode::VariableDeclaration(ty, name, value, StorageClass::Auto) => {
+            let value = match value {
+                Some(value) => compile_value_as(*value, &ty, compiler),
+                None => Value::Null,
+            };
+            compiler.variables.insert(name.clone(), ty.clone());
+            statements.push(Operand::DeclareVariable(ty.into_ir(), name.clone(), value));
+            statements.append(&mut compiler.describe_local(&name, &ty, false));
         }
         ASTNode::Add(lhs, rhs) => {
             statements.push(Operand::Add(
@@ -128,17 +1734,180 @@ fn compile_node(node: ASTNode, compiler: &mut Compiler) -> Vec<Operand> {
             if value.is_none() {
                 statements.push(Operand::Return(Value::Null));
             } else {
-                let value = compile_value(*value.unwrap(), compiler);
+                let return_type = compiler.return_type.clone();
+                let value = compile_value_as(*value.unwrap(), &return_type, compiler);
                 statements.push(Operand::Return(value));
             }
         }
-        ASTNode::Value(value) => {}
+        ASTNode::Ternary(predicate, main, other) => {
+            let main_body = compile_node(*main, compiler);
+            let other_body = compile_node(*other, compiler);
+            statements.append(&mut compile_branches(
+                *predicate, main_body, other_body, compiler,
+            ));
+        }
+        ASTNode::Comma(lhs, rhs) => {
+            statements.append(&mut compile_node(*lhs, compiler));
+            statements.append(&mut compile_node(*rhs, compiler));
+        }
+        ASTNode::Value(ASTValue::FunctionCall(name, values)) => {
+            statements.append(&mut compile_node(ASTNode::FunctionCall(name, values), compiler));
+        }
+        ASTNode::Value(ASTValue::IndirectCall(callee, values)) => {
+            statements.append(&mut compile_node(ASTNode::IndirectCall(callee, values), compiler));
+        }
+        ASTNode::Cast(Type::VOID, value) => {
+            statements.append(&mut compile_node(*value, compiler));
+        }
+        // Any other expression statement is evaluated into a temporary for its side effects
+        expression => {
+            let ty = compiler.type_of(&expression);
+            let value = compile_value(expression, compiler);
+            let name = compiler.temporary(ty.clone());
+            statements.push(Operand::DeclareVariable(ty.into_ir(), name, value));
+        }
     }
 
     statements
 }
 
-fn compile_list(ast: Vec<ASTNode>, compiler: &mut Compiler) -> Vec<Operand> {
+/// Compiles the initialiser of a variable with static storage, which has to be a constant
+fn compile_constant(value: ASTNode, ty: &Type, compiler: &mut CompileState) -> Value {
+    let constant = fold::evaluate(&value);
+
+    let is_constant = match &value {
+        _ if constant.is_some() => true,
+        ASTNode::Value(ASTValue::FloatValue(_) | ASTValue::StringValue(_)) => true,
+        // Addresses of functions and of variables with static storage are resolved by the linker
+        ASTNode::Value(ASTValue::Ref(name)) => {
+            compiler.is_function(name) || compiler.globals.contains_key(&compiler.resolve(name))
+        }
+        ASTNode::Value(ASTValue::StringLiteral(name)) => {
+            compiler.is_function(name)
+                || (compiler.globals.contains_key(&compiler.resolve(name))
+                    && matches!(compiler.lookup(name), Some(Type::ARRAY(_, _))))
+        }
+        _ => false,
+    };
+
+    if !is_constant {
+        eprintln!("Error: Initialiser element is not constant: {value:?}");
+        panic!()
+    }
+
+    // Floating point values are emitted as their bit pattern, rather than through a float define
+    if ty.is_floating() {
+        let value = match (constant, value) {
+            (Some(constant), _) => constant.as_f64(),
+            (None, ASTNode::Value(ASTValue::FloatValue(value))) => value,
+            (None, value) => {
+                eprintln!("Error: Cannot initialise {ty:?} with {value:?}");
+                panic!()
+            }
+        };
+
+        return match ty.unqualified() {
+            Type::FLOAT => Value::Int((value as f32).to_bits().to_string()),
+            _ => Value::Int(value.to_bits().to_string()),
+        };
+    }
+
+    compile_value_as(value, ty, compiler)
+}
+
+/// Compiles a variable with static storage, placed in `.rodata` if const, `.data` if initialised
+/// and `.bss` otherwise
+fn compile_global(
+    ty: Type,
+    name: String,
+    value: Option<Box<ASTNode>>,
+    storage: StorageClass,
+    compiler: &mut CompileState,
+) -> Vec<Operand> {
+    compiler.globals.insert(name.clone(), ty.clone());
+
+    if storage == StorageClass::Extern {
+        return compiler.declare_extern(&name);
+    }
+
+    let value = match value {
+        Some(value) => compile_constant(*value, &ty, compiler),
+        None => Value::Null,
+    };
+
+    let mut operands = vec![];
+    if storage != StorageClass::Static {
+        operands.push(Operand::InlineAssembly(format!("global {name}")));
+    }
+    if ty.is_const() {
+        operands.push(Operand::DeclareGlobalConstant(ty.into_ir(), name, value));
+    } else {
+        operands.push(Operand::DeclareGlobal(ty.into_ir(), name, value));
+    }
+    operands
+}
+
+/// Decodes the escape sequences of a string literal into its bytes, without the terminator
+fn string_bytes(value: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+
+        bytes.push(escaped_char(&mut chars) as u8);
+    }
+
+    bytes
+}
+
+/// Emits the string literals to `.rodata`, writing to them is undefined so a literal that is
+/// the tail of a longer one is just a label into it
+fn compile_strings(strings: &[String]) -> Vec<Operand> {
+    let mut strings = strings
+        .iter()
+        .enumerate()
+        .map(|(i, value)| (format!("_SD{i}"), string_bytes(value)))
+        .collect::<Vec<(String, Vec<u8>)>>();
+    strings.sort_by_key(|(_, bytes)| std::cmp::Reverse(bytes.len()));
+
+    let mut placed: Vec<(String, Vec<u8>)> = vec![];
+    let mut aliases = vec![];
+
+    for (label, bytes) in strings {
+        match placed.iter().find(|(_, other)| other.ends_with(&bytes)) {
+            Some((other_label, other)) => aliases.push(format!(
+                "{label} equ {other_label} + {}",
+                other.len() - bytes.len()
+            )),
+            None => placed.push((label, bytes)),
+        }
+    }
+
+    if placed.is_empty() {
+        return vec![];
+    }
+
+    let mut operands = vec![Operand::InlineAssembly("section .rodata".to_string())];
+    for (label, bytes) in placed {
+        let bytes = bytes
+            .iter()
+            .chain([0].iter())
+            .map(|b| b.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        operands.push(Operand::InlineAssembly(format!("{label}: db {bytes}")));
+    }
+    operands.extend(aliases.into_iter().map(Operand::InlineAssembly));
+    operands.push(Operand::InlineAssembly("section .text".to_string()));
+    operands
+}
+
+fn compile_list(ast: Vec<ASTNode>, compiler: &mut CompileState) -> Vec<Operand> {
     let mut statements = vec![];
 
     for node in ast {
@@ -153,12 +1922,35 @@ pub fn add_header(s: String) -> String {
 }
 
 pub fn compile(ast: Vec<ASTNode>) -> String {
-    let mut ir_compiler = Compiler::new();
+    compile_with_options(ast, CompileOptions::default())
+}
+
+pub fn compile_with_options(ast: Vec<ASTNode>, options: CompileOptions) -> String {
+    let mut state = CompileState::new(options);
+    state.declare_file_scope(&ast);
 
     for node in ast {
-        let mut operands = compile_node(node, &mut ir_compiler);
-        ir_compiler.operands.append(&mut operands);
+        let mut operands = match node {
+            ASTNode::VariableDeclaration(ty, name, value, storage) => {
+                if state.options.debug_info && storage != StorageClass::Extern {
+                    state.debug.variable(&name, &ty, dwarf::Location::Static(name.clone()), false);
+                }
+                compile_global(ty, name, value, storage, &mut state)
+            }
+            node => compile_node(node, &mut state),
+        };
+        state.compiler.operands.append(&mut operands);
     }
 
-    ir_compiler.compile()
+    state.compiler.operands.append(&mut state.file_operands);
+    state
+        .compiler
+        .operands
+        .append(&mut compile_strings(&state.strings));
+    let mut asm = state.compiler.compile();
+    if state.options.debug_info {
+        let directory = std::env::current_dir().map(|path| path.display().to_string()).unwrap_or_default();
+        asm.push_str(&state.debug.render(&state.options.file, &directory));
+    }
+    asm
 }
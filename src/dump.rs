@@ -0,0 +1,413 @@
+use std::fmt::Write;
+
+use low_level_ir::CompareOperation;
+
+use crate::{
+    compile::{CompileOptions, CompileState},
+    parse::{ASTNode, ASTValue, StorageClass, Type},
+    tokenise::{Location, Token},
+};
+
+/// A node of the tree printed by `--dump-ast`, every `ASTNode` and `ASTValue` becoming one
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpNode {
+    pub kind: String,
+    /// Names, operators, literals and the like, in a fixed order for each kind
+    pub attributes: Vec<(String, String)>,
+    /// The type of an expression after decay, or the declared type of a declaration
+    pub ty: Option<Type>,
+    pub location: Option<Location>,
+    pub children: Vec<DumpNode>,
+}
+
+impl DumpNode {
+    fn new(kind: &str) -> Self {
+        Self { kind: kind.to_string(), attributes: vec![], ty: None, location: None, children: vec![] }
+    }
+
+    fn attribute(mut self, name: &str, value: impl ToString) -> Self {
+        self.attributes.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn typed(mut self, ty: Type) -> Self {
+        self.ty = Some(ty);
+        self
+    }
+}
+
+/// Builds the tree for `ast`, typing it in the scope of the `prelude` it is compiled with
+pub fn ast_tree(prelude: &[ASTNode], ast: &[ASTNode]) -> Vec<DumpNode> {
+    let mut state = CompileState::new(CompileOptions::default());
+    state.declare_file_scope(prelude);
+    state.declare_file_scope(ast);
+
+    dump_list(ast, &mut state)
+}
+
+/// The `SourceLine` markers are folded into the location of the statement after them
+fn dump_list(nodes: &[ASTNode], state: &mut CompileState) -> Vec<DumpNode> {
+    let mut dumped = vec![];
+    let mut location = None;
+
+    for node in nodes {
+        if let ASTNode::SourceLine(file, line) = node {
+            location = Some(Location { file: file.clone(), line: *line });
+            continue;
+        }
+
+        let mut node = dump_node(node, state);
+        node.location = location.take();
+        dumped.push(node);
+    }
+
+    dumped
+}
+
+fn dump_node(node: &ASTNode, state: &mut CompileState) -> DumpNode {
+    match node {
+        ASTNode::FunctionDeclaration(ty, name, body, params, variadic, storage) => {
+            state.enter_function(params);
+
+            let mut dumped = DumpNode::new("FunctionDeclaration")
+                .attribute("name", name)
+                .attribute("storage", storage_name(*storage))
+                .attribute("variadic", variadic)
+                .typed(ty.clone());
+            dumped.children = params
+                .iter()
+                .map(|(name, ty)| DumpNode::new("Parameter").attribute("name", name).typed(ty.clone()))
+                .collect();
+            dumped.children.append(&mut dump_list(body, state));
+            dumped
+        }
+        ASTNode::FunctionPrototype(ty, name, storage) => DumpNode::new("FunctionPrototype")
+            .attribute("name", name)
+            .attribute("storage", storage_name(*storage))
+            .typed(ty.clone()),
+        ASTNode::SourceLine(file, line) => {
+            DumpNode::new("SourceLine").attribute("file", file).attribute("line", line)
+        }
+        ASTNode::VariableDeclaration(ty, name, value, storage) => {
+            let mut dumped = DumpNode::new("VariableDeclaration")
+                .attribute("name", name)
+                .attribute("storage", storage_name(*storage))
+                .typed(ty.clone());
+            // The initialiser can't refer to the variable, except through sizeof
+            dumped.children = value.iter().map(|value| dump_node(value, state)).collect();
+            state.declare_local(name, ty);
+            dumped
+        }
+        ASTNode::If { predicate, main_body, else_body } => {
+            let mut dumped = DumpNode::new("If");
+            dumped.children.push(dump_node(predicate, state));
+
+            let mut main = DumpNode::new("Then");
+            main.children = dump_list(main_body, state);
+            dumped.children.push(main);
+
+            if let Some(else_body) = else_body {
+                let mut other = DumpNode::new("Else");
+                other.children = dump_list(else_body, state);
+                dumped.children.push(other);
+            }
+            dumped
+        }
+        ASTNode::Return(value) => {
+            let mut dumped = DumpNode::new("Return");
+            dumped.children = value.iter().map(|value| dump_node(value, state)).collect();
+            dumped
+        }
+        ASTNode::InlineAssembly(template) => DumpNode::new("InlineAssembly").attribute("template", template),
+        ASTNode::ExtendedAssembly { template, outputs, inputs, clobbers } => {
+            let mut dumped = DumpNode::new("ExtendedAssembly")
+                .attribute("template", template)
+                .attribute("clobbers", clobbers.join(","));
+            for (kind, operands) in [("Output", outputs), ("Input", inputs)] {
+                for (constraint, value) in operands {
+                    let mut operand = DumpNode::new(kind).attribute("constraint", constraint);
+                    operand.children.push(dump_node(value, state));
+                    dumped.children.push(operand);
+                }
+            }
+            dumped
+        }
+        // Calls used as statements, typed like the same call in an expression
+        ASTNode::FunctionCall(name, arguments) => {
+            dump_node(&ASTNode::Value(ASTValue::FunctionCall(name.clone(), arguments.clone())), state)
+        }
+        ASTNode::IndirectCall(callee, arguments) => {
+            dump_node(&ASTNode::Value(ASTValue::IndirectCall(callee.clone(), arguments.clone())), state)
+        }
+        node => dump_expression(node, state).typed(state.type_of(node)),
+    }
+}
+
+fn storage_name(storage: StorageClass) -> &'static str {
+    match storage {
+        StorageClass::Auto => "auto",
+        StorageClass::Static => "static",
+        StorageClass::Extern => "extern",
+    }
+}
+
+/// Expressions, which the caller gives their type
+fn dump_expression(node: &ASTNode, state: &mut CompileState) -> DumpNode {
+    let (kind, children): (&str, Vec<&ASTNode>) = match node {
+        ASTNode::Value(value) => return dump_value(value, state),
+        ASTNode::Add(lhs, rhs) => ("Add", vec![&**lhs, &**rhs]),
+        ASTNode::Sub(lhs, rhs) => ("Sub", vec![&**lhs, &**rhs]),
+        ASTNode::Mul(lhs, rhs) => ("Mul", vec![&**lhs, &**rhs]),
+        ASTNode::Div(lhs, rhs) => ("Div", vec![&**lhs, &**rhs]),
+        ASTNode::Mod(lhs, rhs) => ("Mod", vec![&**lhs, &**rhs]),
+        ASTNode::BitAnd(lhs, rhs) => ("BitAnd", vec![&**lhs, &**rhs]),
+        ASTNode::BitOr(lhs, rhs) => ("BitOr", vec![&**lhs, &**rhs]),
+        ASTNode::BitXor(lhs, rhs) => ("BitXor", vec![&**lhs, &**rhs]),
+        ASTNode::BitNot(value) => ("BitNot", vec![&**value]),
+        ASTNode::ShiftLeft(lhs, rhs) => ("ShiftLeft", vec![&**lhs, &**rhs]),
+        ASTNode::ShiftRight(lhs, rhs) => ("ShiftRight", vec![&**lhs, &**rhs]),
+        ASTNode::Compare(comparison) => {
+            let operator = match comparison.operation {
+                CompareOperation::EQ => "==",
+                CompareOperation::NEQ => "!=",
+                CompareOperation::GT => ">",
+                CompareOperation::LT => "<",
+                CompareOperation::GTE => ">=",
+                CompareOperation::LTE => "<=",
+                // The unsigned comparisons are only chosen while compiling
+                _ => unreachable!(),
+            };
+            let mut dumped = DumpNode::new("Compare").attribute("operator", operator);
+            dumped.children = vec![dump_node(&comparison.lhs, state), dump_node(&comparison.rhs, state)];
+            return dumped;
+        }
+        ASTNode::And(lhs, rhs) => ("And", vec![&**lhs, &**rhs]),
+        ASTNode::Or(lhs, rhs) => ("Or", vec![&**lhs, &**rhs]),
+        ASTNode::Not(value) => ("Not", vec![&**value]),
+        ASTNode::Deref(pointer) => ("Deref", vec![&**pointer]),
+        ASTNode::Increment { lvalue, decrement, postfix } => {
+            let mut dumped = DumpNode::new("Increment")
+                .attribute("decrement", decrement)
+                .attribute("postfix", postfix);
+            dumped.children.push(dump_node(lvalue, state));
+            return dumped;
+        }
+        ASTNode::SetVariable(lvalue, value) => ("SetVariable", vec![&**lvalue, &**value]),
+        ASTNode::CompoundAssignment(lvalue, operator, value) => {
+            let mut dumped = DumpNode::new("CompoundAssignment").attribute("operator", operator);
+            dumped.children = vec![dump_node(lvalue, state), dump_node(value, state)];
+            return dumped;
+        }
+        ASTNode::Cast(_, value) => ("Cast", vec![&**value]),
+        ASTNode::SizeOf(value) => ("SizeOf", vec![&**value]),
+        // The type operand is a child so it is written like every other type
+        ASTNode::SizeOfType(ty) | ASTNode::AlignOfType(ty) => {
+            let kind = if matches!(node, ASTNode::SizeOfType(_)) { "SizeOfType" } else { "AlignOfType" };
+            let mut dumped = DumpNode::new(kind);
+            dumped.children.push(DumpNode::new("TypeName").typed(ty.clone()));
+            return dumped;
+        }
+        ASTNode::VaStart(list) => ("VaStart", vec![&**list]),
+        ASTNode::VaArg(list, _) => ("VaArg", vec![&**list]),
+        ASTNode::VaEnd(list) => ("VaEnd", vec![&**list]),
+        ASTNode::Ternary(predicate, main, other) => ("Ternary", vec![&**predicate, &**main, &**other]),
+        ASTNode::Comma(lhs, rhs) => ("Comma", vec![&**lhs, &**rhs]),
+        _ => unreachable!(),
+    };
+
+    let mut dumped = DumpNode::new(kind);
+    dumped.children = children.into_iter().map(|child| dump_node(child, state)).collect();
+    dumped
+}
+
+fn dump_value(value: &ASTValue, state: &mut CompileState) -> DumpNode {
+    match value {
+        ASTValue::Deref(name) => DumpNode::new("Deref").attribute("name", name),
+        ASTValue::Ref(name) => DumpNode::new("Ref").attribute("name", name),
+        ASTValue::StringLiteral(name) => DumpNode::new("Variable").attribute("name", name),
+        ASTValue::StringValue(value) => DumpNode::new("String").attribute("value", value),
+        ASTValue::IntValue(value) => DumpNode::new("Int").attribute("value", value),
+        ASTValue::FloatValue(value) => DumpNode::new("Float").attribute("value", value),
+        ASTValue::CharValue(value) => DumpNode::new("Char").attribute("value", value),
+        ASTValue::FunctionCall(name, arguments) => {
+            let mut dumped = DumpNode::new("FunctionCall").attribute("name", name);
+            dumped.children = arguments.iter().map(|argument| dump_node(argument, state)).collect();
+            dumped
+        }
+        ASTValue::IndirectCall(callee, arguments) => {
+            let mut dumped = DumpNode::new("IndirectCall");
+            dumped.children = [&**callee]
+                .into_iter()
+                .chain(arguments)
+                .map(|node| dump_node(node, state))
+                .collect();
+            dumped
+        }
+    }
+}
+
+/// One node per line, children indented under their parent, e.g. `Add : INT`
+pub fn render_tree(nodes: &[DumpNode]) -> String {
+    let mut output = String::new();
+    for node in nodes {
+        render_node(node, 0, &mut output);
+    }
+    output
+}
+
+fn render_node(node: &DumpNode, depth: usize, output: &mut String) {
+    write!(output, "{}{}", "  ".repeat(depth), node.kind).unwrap();
+    for (name, value) in &node.attributes {
+        write!(output, " {name}={value:?}").unwrap();
+    }
+    if let Some(ty) = &node.ty {
+        write!(output, " : {ty:?}").unwrap();
+    }
+    if let Some(location) = &node.location {
+        write!(output, " @ {}:{}", location.file, location.line).unwrap();
+    }
+    output.push('\n');
+
+    for child in &node.children {
+        render_node(child, depth + 1, output);
+    }
+}
+
+/// The tree as a JSON array, every node having the keys `kind`, `attributes`, `type`,
+/// `location` and `children`, with `null` for a missing type or location. Types are written
+/// by `json_type`
+pub fn render_json(nodes: &[DumpNode]) -> String {
+    let nodes = nodes.iter().map(json_node).collect::<Vec<String>>();
+    format!("[{}]\n", nodes.join(","))
+}
+
+fn json_node(node: &DumpNode) -> String {
+    let attributes = node
+        .attributes
+        .iter()
+        .map(|(name, value)| format!("{}:{}", json_string(name), json_string(value)))
+        .collect::<Vec<String>>();
+    let ty = match &node.ty {
+        Some(ty) => json_type(ty),
+        None => "null".to_string(),
+    };
+    let location = match &node.location {
+        Some(location) => json_location(location),
+        None => "null".to_string(),
+    };
+    let children = node.children.iter().map(json_node).collect::<Vec<String>>();
+
+    format!(
+        "{{\"kind\":{},\"attributes\":{{{}}},\"type\":{ty},\"location\":{location},\"children\":[{}]}}",
+        json_string(&node.kind),
+        attributes.join(","),
+        children.join(",")
+    )
+}
+
+/// A type as a JSON object whose `kind` is one of
+/// - `scalar`, with the C `name` such as `"unsigned long"`
+/// - `pointer`, with the pointed to type in `to`
+/// - `array`, with the element type in `of` and the `length`
+/// - `function`, with `returns`, the `parameters` types and whether it is `variadic`
+/// - `const` and `volatile`, with the qualified type in `of`
+pub fn json_type(ty: &Type) -> String {
+    let scalar = |name: &str| format!("{{\"kind\":\"scalar\",\"name\":{}}}", json_string(name));
+
+    match ty {
+        Type::VOID => scalar("void"),
+        Type::BOOL => scalar("_Bool"),
+        Type::CHAR => scalar("char"),
+        Type::UCHAR => scalar("unsigned char"),
+        Type::SHORT => scalar("short"),
+        Type::USHORT => scalar("unsigned short"),
+        Type::INT => scalar("int"),
+        Type::UINT => scalar("unsigned int"),
+        Type::LONG => scalar("long"),
+        Type::ULONG => scalar("unsigned long"),
+        Type::LONGLONG => scalar("long long"),
+        Type::ULONGLONG => scalar("unsigned long long"),
+        Type::FLOAT => scalar("float"),
+        Type::DOUBLE => scalar("double"),
+        Type::PTR(inner) => format!("{{\"kind\":\"pointer\",\"to\":{}}}", json_type(inner)),
+        Type::ARRAY(inner, length) => {
+            format!("{{\"kind\":\"array\",\"of\":{},\"length\":{length}}}", json_type(inner))
+        }
+        Type::FUNC(returns, parameters, variadic) => {
+            let parameters = parameters.iter().map(json_type).collect::<Vec<String>>();
+            format!(
+                "{{\"kind\":\"function\",\"returns\":{},\"parameters\":[{}],\"variadic\":{variadic}}}",
+                json_type(returns),
+                parameters.join(",")
+            )
+        }
+        Type::CONST(inner) => format!("{{\"kind\":\"const\",\"of\":{}}}", json_type(inner)),
+        Type::VOLATILE(inner) => format!("{{\"kind\":\"volatile\",\"of\":{}}}", json_type(inner)),
+    }
+}
+
+/// One token per line with where it starts, e.g. `test.c:3 Keyword "int"`
+pub fn render_tokens(tokens: &[Token], locations: &[Location]) -> String {
+    let mut output = String::new();
+    for (token, location) in tokens.iter().zip(locations) {
+        let (kind, value) = token_parts(token);
+        writeln!(output, "{}:{} {kind} {value:?}", location.file, location.line).unwrap();
+    }
+    output
+}
+
+/// The tokens as a JSON array of `{"kind", "value", "location"}` objects
+pub fn render_tokens_json(tokens: &[Token], locations: &[Location]) -> String {
+    let tokens = tokens
+        .iter()
+        .zip(locations)
+        .map(|(token, location)| {
+            let (kind, value) = token_parts(token);
+            format!(
+                "{{\"kind\":{},\"value\":{},\"location\":{}}}",
+                json_string(kind),
+                json_string(&value),
+                json_location(location)
+            )
+        })
+        .collect::<Vec<String>>();
+    format!("[{}]\n", tokens.join(","))
+}
+
+fn token_parts(token: &Token) -> (&'static str, String) {
+    match token {
+        Token::Int(value, suffix) => {
+            let unsigned = if suffix.unsigned { "u" } else { "" };
+            let long = if suffix.long { "l" } else { "" };
+            ("Int", format!("{value}{unsigned}{long}"))
+        }
+        Token::Float(value, is_float) => ("Float", format!("{value}{}", if *is_float { "f" } else { "" })),
+        Token::CharValue(value) => ("Char", value.to_string()),
+        Token::StringValue(value) => ("String", value.clone()),
+        Token::StringLiteral(value) => ("Identifier", value.clone()),
+        Token::Keyword(value) => ("Keyword", value.clone()),
+        Token::Punctuation(value) => ("Punctuation", value.to_string()),
+        Token::MathSymbol(value) => ("Operator", value.clone()),
+        Token::Ellipsis => ("Punctuation", "...".to_string()),
+    }
+}
+
+fn json_location(location: &Location) -> String {
+    format!("{{\"file\":{},\"line\":{}}}", json_string(&location.file), location.line)
+}
+
+fn json_string(value: &str) -> String {
+    let mut output = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(output, "\\u{:04x}", c as u32).unwrap(),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
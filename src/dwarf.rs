@@ -0,0 +1,513 @@
+use crate::parse::Type;
+
+// Tags, attributes, forms and opcodes of DWARF 4 that are used
+const DW_TAG_ARRAY_TYPE: u8 = 0x01;
+const DW_TAG_FORMAL_PARAMETER: u8 = 0x05;
+const DW_TAG_POINTER_TYPE: u8 = 0x0f;
+const DW_TAG_COMPILE_UNIT: u8 = 0x11;
+const DW_TAG_SUBROUTINE_TYPE: u8 = 0x15;
+const DW_TAG_SUBRANGE_TYPE: u8 = 0x21;
+const DW_TAG_BASE_TYPE: u8 = 0x24;
+const DW_TAG_CONST_TYPE: u8 = 0x26;
+const DW_TAG_SUBPROGRAM: u8 = 0x2e;
+const DW_TAG_VARIABLE: u8 = 0x34;
+const DW_TAG_VOLATILE_TYPE: u8 = 0x35;
+
+const DW_AT_LOCATION: u8 = 0x02;
+const DW_AT_NAME: u8 = 0x03;
+const DW_AT_BYTE_SIZE: u8 = 0x0b;
+const DW_AT_STMT_LIST: u8 = 0x10;
+const DW_AT_LOW_PC: u8 = 0x11;
+const DW_AT_HIGH_PC: u8 = 0x12;
+const DW_AT_LANGUAGE: u8 = 0x13;
+const DW_AT_COMP_DIR: u8 = 0x1b;
+const DW_AT_PRODUCER: u8 = 0x25;
+const DW_AT_COUNT: u8 = 0x37;
+const DW_AT_ENCODING: u8 = 0x3e;
+const DW_AT_TYPE: u8 = 0x49;
+const DW_AT_RANGES: u8 = 0x55;
+
+const DW_FORM_ADDR: u8 = 0x01;
+const DW_FORM_DATA4: u8 = 0x06;
+const DW_FORM_STRING: u8 = 0x08;
+const DW_FORM_DATA1: u8 = 0x0b;
+const DW_FORM_REF4: u8 = 0x13;
+const DW_FORM_SEC_OFFSET: u8 = 0x17;
+const DW_FORM_EXPRLOC: u8 = 0x18;
+
+const DW_ATE_BOOLEAN: u8 = 0x02;
+const DW_ATE_FLOAT: u8 = 0x04;
+const DW_ATE_SIGNED: u8 = 0x05;
+const DW_ATE_SIGNED_CHAR: u8 = 0x06;
+const DW_ATE_UNSIGNED: u8 = 0x07;
+const DW_ATE_UNSIGNED_CHAR: u8 = 0x08;
+
+const DW_LANG_C99: u8 = 0x0c;
+
+const DW_OP_ADDR: u8 = 0x03;
+const DW_OP_DEREF: u8 = 0x06;
+const DW_OP_PLUS: u8 = 0x22;
+const DW_OP_BREG6: u8 = 0x76;
+
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNS_SET_FILE: u8 = 0x04;
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
+
+/// The abbreviation codes, each describing one kind of entry
+#[derive(Clone, Copy)]
+enum Abbreviation {
+    CompileUnit = 1,
+    Subprogram,
+    VoidSubprogram,
+    Variable,
+    Parameter,
+    BaseType,
+    Pointer,
+    VoidPointer,
+    Const,
+    VoidConst,
+    Volatile,
+    VoidVolatile,
+    Array,
+    Subrange,
+    Subroutine,
+    VoidSubroutine,
+}
+
+/// An abbreviation's code, tag, whether it has children and its attributes with their forms
+type AbbreviationEntry = (Abbreviation, u8, bool, &'static [(u8, u8)]);
+
+const ABBREVIATIONS: [AbbreviationEntry; 16] = [
+    (
+        Abbreviation::CompileUnit,
+        DW_TAG_COMPILE_UNIT,
+        true,
+        &[
+            (DW_AT_PRODUCER, DW_FORM_STRING),
+            (DW_AT_LANGUAGE, DW_FORM_DATA1),
+            (DW_AT_NAME, DW_FORM_STRING),
+            (DW_AT_COMP_DIR, DW_FORM_STRING),
+            (DW_AT_LOW_PC, DW_FORM_ADDR),
+            (DW_AT_RANGES, DW_FORM_SEC_OFFSET),
+            (DW_AT_STMT_LIST, DW_FORM_SEC_OFFSET),
+        ],
+    ),
+    (
+        Abbreviation::Subprogram,
+        DW_TAG_SUBPROGRAM,
+        true,
+        &[
+            (DW_AT_NAME, DW_FORM_STRING),
+            (DW_AT_LOW_PC, DW_FORM_ADDR),
+            (DW_AT_HIGH_PC, DW_FORM_ADDR),
+            (DW_AT_TYPE, DW_FORM_REF4),
+        ],
+    ),
+    (
+        Abbreviation::VoidSubprogram,
+        DW_TAG_SUBPROGRAM,
+        true,
+        &[(DW_AT_NAME, DW_FORM_STRING), (DW_AT_LOW_PC, DW_FORM_ADDR), (DW_AT_HIGH_PC, DW_FORM_ADDR)],
+    ),
+    (
+        Abbreviation::Variable,
+        DW_TAG_VARIABLE,
+        false,
+        &[(DW_AT_NAME, DW_FORM_STRING), (DW_AT_TYPE, DW_FORM_REF4), (DW_AT_LOCATION, DW_FORM_EXPRLOC)],
+    ),
+    (
+        Abbreviation::Parameter,
+        DW_TAG_FORMAL_PARAMETER,
+        false,
+        &[(DW_AT_NAME, DW_FORM_STRING), (DW_AT_TYPE, DW_FORM_REF4), (DW_AT_LOCATION, DW_FORM_EXPRLOC)],
+    ),
+    (
+        Abbreviation::BaseType,
+        DW_TAG_BASE_TYPE,
+        false,
+        &[(DW_AT_NAME, DW_FORM_STRING), (DW_AT_ENCODING, DW_FORM_DATA1), (DW_AT_BYTE_SIZE, DW_FORM_DATA1)],
+    ),
+    (
+        Abbreviation::Pointer,
+        DW_TAG_POINTER_TYPE,
+        false,
+        &[(DW_AT_BYTE_SIZE, DW_FORM_DATA1), (DW_AT_TYPE, DW_FORM_REF4)],
+    ),
+    (Abbreviation::VoidPointer, DW_TAG_POINTER_TYPE, false, &[(DW_AT_BYTE_SIZE, DW_FORM_DATA1)]),
+    (Abbreviation::Const, DW_TAG_CONST_TYPE, false, &[(DW_AT_TYPE, DW_FORM_REF4)]),
+    (Abbreviation::VoidConst, DW_TAG_CONST_TYPE, false, &[]),
+    (Abbreviation::Volatile, DW_TAG_VOLATILE_TYPE, false, &[(DW_AT_TYPE, DW_FORM_REF4)]),
+    (Abbreviation::VoidVolatile, DW_TAG_VOLATILE_TYPE, false, &[]),
+    (Abbreviation::Array, DW_TAG_ARRAY_TYPE, true, &[(DW_AT_TYPE, DW_FORM_REF4)]),
+    (Abbreviation::Subrange, DW_TAG_SUBRANGE_TYPE, false, &[(DW_AT_COUNT, DW_FORM_DATA4)]),
+    (Abbreviation::Subroutine, DW_TAG_SUBROUTINE_TYPE, false, &[(DW_AT_TYPE, DW_FORM_REF4)]),
+    (Abbreviation::VoidSubroutine, DW_TAG_SUBROUTINE_TYPE, false, &[]),
+];
+
+/// Where a variable is kept
+#[derive(Debug, Clone)]
+pub enum Location {
+    /// In the frame, at `rbp` plus the offset the function stores at this label on entry
+    Frame(String),
+    /// At a fixed label
+    Static(String),
+}
+
+#[derive(Debug)]
+struct Variable {
+    name: String,
+    ty: Type,
+    location: Location,
+    parameter: bool,
+}
+
+#[derive(Debug)]
+struct Function {
+    name: String,
+    return_type: Type,
+    variables: Vec<Variable>,
+    /// The label, file and line of each row of the line table in this function
+    lines: Vec<(String, usize, u32)>,
+}
+
+/// The debug information for `-g`, collected while compiling and written out as DWARF 4 in
+/// NASM data directives. Code addresses are labels put in the text by the compiler
+#[derive(Debug, Default)]
+pub struct DebugInfo {
+    /// Source file names, numbered from 1 in the line table
+    files: Vec<String>,
+    functions: Vec<Function>,
+    globals: Vec<Variable>,
+    /// Whether the last function started hasn't ended yet
+    in_function: bool,
+    /// The last line seen outside a function, which a following definition starts on
+    line: Option<(usize, u32)>,
+    labels: usize,
+}
+
+impl DebugInfo {
+    fn file_index(&mut self, file: &str) -> usize {
+        match self.files.iter().position(|name| name == file) {
+            Some(index) => index + 1,
+            None => {
+                self.files.push(file.to_string());
+                self.files.len()
+            }
+        }
+    }
+
+    fn label(&mut self, kind: &str) -> String {
+        self.labels += 1;
+        format!("..@dbg_{kind}{}", self.labels)
+    }
+
+    /// Records that the code that follows comes from `line` of `file`, giving the label to put
+    /// before it. Lines outside a function only give the line the next one starts on
+    pub fn line(&mut self, file: &str, line: u32) -> Option<String> {
+        let file = self.file_index(file);
+        if !self.in_function {
+            self.line = Some((file, line));
+            return None;
+        }
+
+        let label = self.label("line");
+        self.functions.last_mut().unwrap().lines.push((label.clone(), file, line));
+        Some(format!("{label}:"))
+    }
+
+    /// Starts a function whose code begins at the label `name`
+    pub fn function(&mut self, name: &str, return_type: &Type) {
+        let lines = self.line.iter().map(|(file, line)| (name.to_string(), *file, *line)).collect();
+        self.functions.push(Function {
+            name: name.to_string(),
+            return_type: return_type.clone(),
+            variables: vec![],
+            lines,
+        });
+        self.in_function = true;
+    }
+
+    /// Ends the current function, giving the label to put after its code
+    pub fn end_function(&mut self) -> String {
+        self.in_function = false;
+        format!("{}:", Self::end_label(self.functions.len() - 1))
+    }
+
+    fn end_label(function: usize) -> String {
+        format!("..@dbg_end{function}")
+    }
+
+    /// A label for a frame offset, which `Location::Frame` reads
+    pub fn frame_slot(&mut self) -> String {
+        self.labels += 1;
+        format!("_DBG{}", self.labels)
+    }
+
+    /// Describes a variable of the current function, or a file scope one outside of functions
+    pub fn variable(&mut self, name: &str, ty: &Type, location: Location, parameter: bool) {
+        let variable = Variable { name: name.to_string(), ty: ty.clone(), location, parameter };
+        match self.functions.last_mut().filter(|_| self.in_function) {
+            Some(function) => function.variables.push(variable),
+            None => self.globals.push(variable),
+        }
+    }
+
+    /// The `.debug_abbrev`, `.debug_info`, `.debug_ranges` and `.debug_line` sections for the
+    /// translation unit `file`, compiled in `directory`
+    pub fn render(&self, file: &str, directory: &str) -> String {
+        let mut output = String::new();
+
+        output.push_str(&section(".debug_abbrev"));
+        output.push_str("..@dbg_abbrev:\n");
+        for (code, tag, children, attributes) in ABBREVIATIONS {
+            output.push_str(&format!("    db {}, {tag}, {}\n", code as u8, children as u8));
+            for (attribute, form) in attributes {
+                output.push_str(&format!("    db {attribute}, {form}\n"));
+            }
+            output.push_str("    db 0, 0\n");
+        }
+        output.push_str("    db 0\n");
+
+        output.push_str(&section(".debug_info"));
+        output.push_str("..@dbg_info:\n");
+        output.push_str("    dd ..@dbg_info_end - ..@dbg_info - 4\n");
+        output.push_str("    dw 4\n");
+        output.push_str("    dd ..@dbg_abbrev\n");
+        output.push_str("    db 8\n");
+        output.push_str(&entry(Abbreviation::CompileUnit));
+        output.push_str(&string("c_compiler"));
+        output.push_str(&format!("    db {DW_LANG_C99}\n"));
+        output.push_str(&string(file));
+        output.push_str(&string(directory));
+        output.push_str("    dq 0\n");
+        output.push_str("    dd ..@dbg_ranges\n");
+        output.push_str("    dd ..@dbg_line_table\n");
+
+        let mut types = vec![];
+        for variable in &self.globals {
+            output.push_str(&render_variable(variable, &mut types));
+        }
+        for (index, function) in self.functions.iter().enumerate() {
+            match type_reference(&function.return_type, &mut types) {
+                Some(reference) => {
+                    output.push_str(&entry(Abbreviation::Subprogram));
+                    output.push_str(&string(&function.name));
+                    output.push_str(&format!("    dq {}, {}\n", function.name, Self::end_label(index)));
+                    output.push_str(&reference);
+                }
+                None => {
+                    output.push_str(&entry(Abbreviation::VoidSubprogram));
+                    output.push_str(&string(&function.name));
+                    output.push_str(&format!("    dq {}, {}\n", function.name, Self::end_label(index)));
+                }
+            }
+            for variable in &function.variables {
+                output.push_str(&render_variable(variable, &mut types));
+            }
+            output.push_str("    db 0\n");
+        }
+
+        // Types refer to others by label, so the list can grow while it is written
+        let mut index = 0;
+        while index < types.len() {
+            let ty = types[index].clone();
+            output.push_str(&format!("..@dbg_type{index}:\n"));
+            output.push_str(&render_type(&ty, &mut types));
+            index += 1;
+        }
+        output.push_str("    db 0\n");
+        output.push_str("..@dbg_info_end:\n");
+
+        // Relative to the compile unit's low_pc of 0
+        output.push_str(&section(".debug_ranges"));
+        output.push_str("..@dbg_ranges:\n");
+        for (index, function) in self.functions.iter().enumerate() {
+            output.push_str(&format!("    dq {}, {}\n", function.name, Self::end_label(index)));
+        }
+        output.push_str("    dq 0, 0\n");
+
+        output.push_str(&section(".debug_line"));
+        output.push_str(&self.render_line_table());
+        output
+    }
+
+    /// The line number program, one sequence for each function
+    fn render_line_table(&self) -> String {
+        let mut output = String::new();
+        output.push_str("..@dbg_line_table:\n");
+        output.push_str("    dd ..@dbg_line_end - ..@dbg_line_table - 4\n");
+        output.push_str("    dw 4\n");
+        output.push_str("    dd ..@dbg_line_program - ..@dbg_line_header\n");
+        output.push_str("..@dbg_line_header:\n");
+        // Instruction length, operations per instruction, is_stmt, line base, line range, and
+        // the first special opcode, none of which are used, then the standard opcode lengths
+        output.push_str("    db 1, 1, 1, -5, 14, 13\n");
+        output.push_str("    db 0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1\n");
+        // No include directories, then each file with no directory, time or length
+        output.push_str("    db 0\n");
+        for file in &self.files {
+            output.push_str(&string(file));
+            output.push_str("    db 0, 0, 0\n");
+        }
+        output.push_str("    db 0\n");
+        output.push_str("..@dbg_line_program:\n");
+
+        for (index, function) in self.functions.iter().enumerate() {
+            // Every sequence starts at line 1 of file 1
+            let (mut current_file, mut current_line) = (1, 1);
+            for (label, file, line) in &function.lines {
+                if *file != current_file {
+                    output.push_str(&format!("    db {DW_LNS_SET_FILE}, {}\n", bytes(&uleb128(*file as u64))));
+                    current_file = *file;
+                }
+                if *line != current_line {
+                    let delta = *line as i64 - current_line as i64;
+                    output.push_str(&format!("    db {DW_LNS_ADVANCE_LINE}, {}\n", bytes(&sleb128(delta))));
+                    current_line = *line;
+                }
+                output.push_str(&format!("    db 0, 9, {DW_LNE_SET_ADDRESS}\n    dq {label}\n"));
+                output.push_str(&format!("    db {DW_LNS_COPY}\n"));
+            }
+            output.push_str(&format!("    db 0, 9, {DW_LNE_SET_ADDRESS}\n    dq {}\n", Self::end_label(index)));
+            output.push_str(&format!("    db 0, 1, {DW_LNE_END_SEQUENCE}\n"));
+        }
+        output.push_str("..@dbg_line_end:\n");
+        output
+    }
+}
+
+fn section(name: &str) -> String {
+    format!("section {name} progbits noalloc noexec nowrite align=1\n")
+}
+
+fn entry(abbreviation: Abbreviation) -> String {
+    format!("    db {}\n", abbreviation as u8)
+}
+
+/// A null terminated string, written as numbers so any character can be in it
+fn string(value: &str) -> String {
+    format!("    db {}\n", bytes(&[value.as_bytes(), &[0]].concat()))
+}
+
+fn bytes(values: &[u8]) -> String {
+    values.iter().map(u8::to_string).collect::<Vec<String>>().join(", ")
+}
+
+fn uleb128(mut value: u64) -> Vec<u8> {
+    let mut encoded = vec![];
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            encoded.push(byte);
+            return encoded;
+        }
+        encoded.push(byte | 0x80);
+    }
+}
+
+fn sleb128(mut value: i64) -> Vec<u8> {
+    let mut encoded = vec![];
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            encoded.push(byte);
+            return encoded;
+        }
+        encoded.push(byte | 0x80);
+    }
+}
+
+/// A `DW_FORM_ref4` to the entry for `ty`, which is added to `types` if it isn't there yet.
+/// There is no entry for void, so it gives `None`
+fn type_reference(ty: &Type, types: &mut Vec<Type>) -> Option<String> {
+    if *ty == Type::VOID {
+        return None;
+    }
+
+    let index = match types.iter().position(|other| other == ty) {
+        Some(index) => index,
+        None => {
+            types.push(ty.clone());
+            types.len() - 1
+        }
+    };
+    Some(format!("    dd ..@dbg_type{index} - ..@dbg_info\n"))
+}
+
+fn render_variable(variable: &Variable, types: &mut Vec<Type>) -> String {
+    let abbreviation = if variable.parameter { Abbreviation::Parameter } else { Abbreviation::Variable };
+    let mut output = entry(abbreviation);
+    output.push_str(&string(&variable.name));
+    // Variables can't be void, a parameter list of just void having no parameters
+    output.push_str(&type_reference(&variable.ty, types).unwrap());
+
+    match &variable.location {
+        Location::Frame(slot) => {
+            // rbp plus the offset at the slot
+            output.push_str(&format!("    db 13, {DW_OP_BREG6}, 0, {DW_OP_ADDR}\n    dq {slot}\n"));
+            output.push_str(&format!("    db {DW_OP_DEREF}, {DW_OP_PLUS}\n"));
+        }
+        Location::Static(label) => output.push_str(&format!("    db 9, {DW_OP_ADDR}\n    dq {label}\n")),
+    }
+    output
+}
+
+/// The name and `DW_ATE` encoding of a scalar type
+fn base_type(ty: &Type) -> (&'static str, u8) {
+    match ty {
+        Type::BOOL => ("_Bool", DW_ATE_BOOLEAN),
+        Type::CHAR => ("char", DW_ATE_SIGNED_CHAR),
+        Type::UCHAR => ("unsigned char", DW_ATE_UNSIGNED_CHAR),
+        Type::SHORT => ("short", DW_ATE_SIGNED),
+        Type::USHORT => ("unsigned short", DW_ATE_UNSIGNED),
+        Type::INT => ("int", DW_ATE_SIGNED),
+        Type::UINT => ("unsigned int", DW_ATE_UNSIGNED),
+        Type::LONG => ("long", DW_ATE_SIGNED),
+        Type::ULONG => ("unsigned long", DW_ATE_UNSIGNED),
+        Type::LONGLONG => ("long long", DW_ATE_SIGNED),
+        Type::ULONGLONG => ("unsigned long long", DW_ATE_UNSIGNED),
+        Type::FLOAT => ("float", DW_ATE_FLOAT),
+        Type::DOUBLE => ("double", DW_ATE_FLOAT),
+        _ => unreachable!(),
+    }
+}
+
+/// The entry for a type other than void, with an abbreviation that leaves out the type it is
+/// built from when that is void
+fn render_type(ty: &Type, types: &mut Vec<Type>) -> String {
+    let with_inner = |with: Abbreviation, without: Abbreviation, inner: &Type, types: &mut Vec<Type>| {
+        match type_reference(inner, types) {
+            Some(reference) => entry(with) + &reference,
+            None => entry(without),
+        }
+    };
+
+    match ty {
+        Type::PTR(inner) => match type_reference(inner, types) {
+            Some(reference) => entry(Abbreviation::Pointer) + "    db 8\n" + &reference,
+            None => entry(Abbreviation::VoidPointer) + "    db 8\n",
+        },
+        Type::CONST(inner) => with_inner(Abbreviation::Const, Abbreviation::VoidConst, inner, types),
+        Type::VOLATILE(inner) => with_inner(Abbreviation::Volatile, Abbreviation::VoidVolatile, inner, types),
+        Type::FUNC(returns, _, _) => with_inner(Abbreviation::Subroutine, Abbreviation::VoidSubroutine, returns, types),
+        Type::ARRAY(inner, count) => {
+            let mut output = entry(Abbreviation::Array);
+            output.push_str(&type_reference(inner, types).unwrap());
+            output.push_str(&entry(Abbreviation::Subrange));
+            output.push_str(&format!("    dd {count}\n"));
+            output.push_str("    db 0\n");
+            output
+        }
+        scalar => {
+            let (name, encoding) = base_type(scalar);
+            let mut output = entry(Abbreviation::BaseType);
+            output.push_str(&string(name));
+            output.push_str(&format!("    db {encoding}, {}\n", scalar.bytes()));
+            output
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use low_level_ir::CompareOperation;
+
+use crate::parse::{ASTNode, ASTValue, Comparison, Type};
+
+/// The value of an integer constant expression along with its type
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constant {
+    /// Sign extended for signed types and zero extended for unsigned ones, so it always holds
+    /// the value the type would, except for `unsigned long` above `i64::MAX`
+    pub value: i64,
+    pub ty: Type,
+}
+
+impl Constant {
+    pub fn new(value: i64, ty: Type) -> Self {
+        let ty = ty.unqualified().clone();
+        let bits = ty.bytes() * 8;
+
+        let value = if ty == Type::BOOL {
+            (value != 0) as i64
+        } else if bits >= 64 {
+            value
+        } else if ty.is_unsigned() {
+            value & ((1 << bits) - 1)
+        } else {
+            (value << (64 - bits)) >> (64 - bits)
+        };
+
+        Self { value, ty }
+    }
+
+    /// Converts the constant as if by assignment, wrapping into the range of `ty`
+    pub fn convert(&self, ty: &Type) -> Self {
+        Self::new(self.value, ty.clone())
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        if self.ty.is_unsigned() {
+            self.value as u64 as f64
+        } else {
+            self.value as f64
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        self.value != 0
+    }
+}
+
+/// The type of an integer literal, `int` if it fits and `long` otherwise
+pub fn literal_type(value: i64) -> Type {
+    if i32::try_from(value).is_ok() {
+        Type::INT
+    } else {
+        Type::LONG
+    }
+}
+
+/// Evaluates an integer constant expression, giving `None` for anything that isn't one.
+/// Arithmetic wraps like the generated code would, while division by zero and shifts by
+/// negative or too large counts are undefined and so aren't constant
+pub fn evaluate(node: &ASTNode) -> Option<Constant> {
+    match node {
+        ASTNode::Value(ASTValue::IntValue(value)) => Some(Constant::new(*value, literal_type(*value))),
+        ASTNode::Value(ASTValue::CharValue(value)) => Some(Constant::new(*value as i64, Type::CHAR)),
+        ASTNode::Cast(ty, value) if ty.is_integer() => match &**value {
+            ASTNode::Value(ASTValue::FloatValue(value)) => Some(Constant::new(*value as i64, ty.clone())),
+            value => Some(evaluate(value)?.convert(ty)),
+        },
+        ASTNode::SizeOfType(ty) => Some(Constant::new(ty.bytes() as i64, Type::ULONG)),
+        ASTNode::AlignOfType(ty) => Some(Constant::new(ty.align() as i64, Type::ULONG)),
+        ASTNode::Not(value) => Some(Constant::new(!evaluate(value)?.is_true() as i64, Type::INT)),
+        ASTNode::BitNot(value) => {
+            let value = evaluate(value)?;
+            let ty = value.ty.promote();
+            Some(Constant::new(!value.value, ty))
+        }
+        ASTNode::And(lhs, rhs) => {
+            let value = evaluate(lhs)?.is_true() && evaluate(rhs)?.is_true();
+            Some(Constant::new(value as i64, Type::INT))
+        }
+        ASTNode::Or(lhs, rhs) => {
+            let value = evaluate(lhs)?.is_true() || evaluate(rhs)?.is_true();
+            Some(Constant::new(value as i64, Type::INT))
+        }
+        ASTNode::Compare(comparison) => evaluate_comparison(comparison),
+        ASTNode::Ternary(predicate, main, other) => {
+            let predicate = evaluate(predicate)?;
+            let main = evaluate(main)?;
+            let other = evaluate(other)?;
+            let ty = main.ty.common(&other.ty);
+
+            Some(if predicate.is_true() { main } else { other }.convert(&ty))
+        }
+        ASTNode::ShiftLeft(lhs, rhs) | ASTNode::ShiftRight(lhs, rhs) => {
+            let lhs = evaluate(lhs)?;
+            let count = evaluate(rhs)?;
+            let ty = lhs.ty.promote();
+            let lhs = lhs.convert(&ty);
+
+            if count.value < 0 || count.value >= (ty.bytes() * 8) as i64 {
+                return None;
+            }
+
+            let value = match (node, ty.is_unsigned()) {
+                (ASTNode::ShiftLeft(_, _), _) => lhs.value.wrapping_shl(count.value as u32),
+                (_, true) => ((lhs.value as u64) >> count.value) as i64,
+                (_, false) => lhs.value >> count.value,
+            };
+            Some(Constant::new(value, ty))
+        }
+        ASTNode::Add(lhs, rhs)
+        | ASTNode::Sub(lhs, rhs)
+        | ASTNode::Mul(lhs, rhs)
+        | ASTNode::Div(lhs, rhs)
+        | ASTNode::Mod(lhs, rhs)
+        | ASTNode::BitAnd(lhs, rhs)
+        | ASTNode::BitOr(lhs, rhs)
+        | ASTNode::BitXor(lhs, rhs) => {
+            let lhs = evaluate(lhs)?;
+            let rhs = evaluate(rhs)?;
+            let ty = lhs.ty.common(&rhs.ty);
+            let (lhs, rhs) = (lhs.convert(&ty).value, rhs.convert(&ty).value);
+
+            let value = match node {
+                ASTNode::Add(_, _) => lhs.wrapping_add(rhs),
+                ASTNode::Sub(_, _) => lhs.wrapping_sub(rhs),
+                ASTNode::Mul(_, _) => lhs.wrapping_mul(rhs),
+                ASTNode::Div(_, _) | ASTNode::Mod(_, _) if rhs == 0 => return None,
+                ASTNode::Div(_, _) if ty.is_unsigned() => ((lhs as u64) / (rhs as u64)) as i64,
+                ASTNode::Mod(_, _) if ty.is_unsigned() => ((lhs as u64) % (rhs as u64)) as i64,
+                ASTNode::Div(_, _) => lhs.wrapping_div(rhs),
+                ASTNode::Mod(_, _) => lhs.wrapping_rem(rhs),
+                ASTNode::BitAnd(_, _) => lhs & rhs,
+                ASTNode::BitOr(_, _) => lhs | rhs,
+                _ => lhs ^ rhs,
+            };
+            Some(Constant::new(value, ty))
+        }
+        _ => None,
+    }
+}
+
+/// Whether `node` is a null pointer constant, an integer constant expression with the value 0,
+/// possibly cast to `void*`
+pub fn is_null_pointer_constant(node: &ASTNode) -> bool {
+    match node {
+        ASTNode::Cast(Type::PTR(inner), value) if *inner.unqualified() == Type::VOID => {
+            is_null_pointer_constant(value)
+        }
+        node => evaluate(node).is_some_and(|constant| constant.value == 0),
+    }
+}
+
+fn evaluate_comparison(comparison: &Comparison) -> Option<Constant> {
+    let lhs = evaluate(&comparison.lhs)?;
+    let rhs = evaluate(&comparison.rhs)?;
+    let ty = lhs.ty.common(&rhs.ty);
+    let (lhs, rhs) = (lhs.convert(&ty).value, rhs.convert(&ty).value);
+
+    let ordering = if ty.is_unsigned() {
+        (lhs as u64).cmp(&(rhs as u64))
+    } else {
+        lhs.cmp(&rhs)
+    };
+
+    let value = match comparison.operation {
+        CompareOperation::EQ => ordering.is_eq(),
+        CompareOperation::NEQ => ordering.is_ne(),
+        CompareOperation::GT => ordering.is_gt(),
+        CompareOperation::LT => ordering.is_lt(),
+        CompareOperation::GTE => ordering.is_ge(),
+        CompareOperation::LTE => ordering.is_le(),
+        _ => return None,
+    };
+    Some(Constant::new(value as i64, Type::INT))
+}
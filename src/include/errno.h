@@ -0,0 +1,10 @@
+// Matches glibc, so it works with or without --libc
+int* __errno_location();
+#define errno (*__errno_location())
+
+#define ENOENT 2
+#define EBADF 9
+#define ENOMEM 12
+#define EACCES 13
+#define EEXIST 17
+#define EINVAL 22
@@ -0,0 +1,2 @@
+// bool, true and false are already keywords
+#define __bool_true_false_are_defined 1
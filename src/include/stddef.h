@@ -0,0 +1,5 @@
+// There is no typedef yet, so the standard types are defined as macros
+#define size_t unsigned long
+#define ptrdiff_t long
+
+#define NULL ((void*)0)
@@ -0,0 +1,19 @@
+// There is no typedef yet, so the standard types are defined as macros
+#define int8_t signed char
+#define int16_t short
+#define int32_t int
+#define int64_t long
+#define uint8_t unsigned char
+#define uint16_t unsigned short
+#define uint32_t unsigned int
+#define uint64_t unsigned long
+#define intptr_t long
+#define uintptr_t unsigned long
+
+#define INT8_MAX 127
+#define INT16_MAX 32767
+#define INT32_MAX 2147483647
+#define INT64_MAX 9223372036854775807
+#define UINT8_MAX 255
+#define UINT16_MAX 65535
+#define UINT32_MAX 4294967295
@@ -0,0 +1,9 @@
+#include <stddef.h>
+#include <stdarg.h>
+
+#define EOF (-1)
+
+int putchar(int character);
+int puts(const char* string);
+// Supports %d, %i, %u, %x, %c, %s and %% without flags, widths or length modifiers
+int printf(const char* format, ...);
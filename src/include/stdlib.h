@@ -0,0 +1,8 @@
+#include <stddef.h>
+
+#define EXIT_SUCCESS 0
+#define EXIT_FAILURE 1
+
+void* malloc(size_t size);
+void free(void* pointer);
+void exit(int status);
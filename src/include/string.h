@@ -0,0 +1,6 @@
+#include <stddef.h>
+
+size_t strlen(const char* string);
+void* memcpy(void* destination, const void* source, size_t count);
+void* memset(void* destination, int value, size_t count);
+int strcmp(const char* lhs, const char* rhs);
@@ -0,0 +1,9 @@
+#include <stddef.h>
+
+#define STDIN_FILENO 0
+#define STDOUT_FILENO 1
+#define STDERR_FILENO 2
+
+long read(int fd, void* buffer, size_t count);
+long write(int fd, const void* buffer, size_t count);
+int close(int fd);
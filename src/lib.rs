@@ -1,3 +1,8 @@
 pub mod tokenise;
 pub mod parse;
-pub mod compile;
\ No newline at end of file
+pub mod compile;
+pub mod fold;
+pub mod abi;
+pub mod preprocess;
+pub mod dump;
+pub mod dwarf;
@@ -1,43 +1,239 @@
-use std::{env, fs::{File, OpenOptions}, io::{Read, Write}, process::Command};
+use std::{collections::HashMap, env, fs::{File, OpenOptions}, io::{Read, Write}, path::Path, process::Command, sync::OnceLock};
 
-use c_compiler::{compile::{add_header, compile}, parse::{parse, ASTNode}, tokenise::tokenise};
+use c_compiler::{compile::{add_header, compile_with_options, CompileOptions}, dump, parse::{parse_with_locations, ASTNode}, preprocess::Preprocessor};
 
 const C_WRAPPER : &str = include_str!("_wrapper_file.c");
+const C_START : &str = include_str!("_start.c");
 
-fn parse_file<S : AsRef<str>>(contents : S) -> Vec<ASTNode>
+/// The lines of each file read, by name
+type Sources = HashMap<String, Vec<String>>;
+
+/// The prelude prepended to every file, tokenised and parsed once on first use
+static PRELUDE : OnceLock<(Vec<ASTNode>, Sources)> = OnceLock::new();
+
+/// Parses a file named `file`, `directory` being where its `#include "name"`s are looked for.
+/// Also gives the lines of it and everything it included
+fn parse_file<S : AsRef<str>>(mut preprocessor : Preprocessor, contents : S, file : &str, directory : Option<&Path>) -> (Vec<ASTNode>, Sources)
+{
+    let (tokens, locations) = preprocessor.preprocess(contents.as_ref(), file, directory);
+    let nodes = parse_with_locations(tokens, locations);
+    (nodes, preprocessor.sources)
+}
+
+fn read_file(path : &str) -> String
+{
+    let mut buffer = String::new();
+    match File::open(path)
+    {
+        Ok(mut file) => { file.read_to_string(&mut buffer).unwrap(); }
+        Err(error) => {
+            eprintln!("Error: Could not open {path}: {error}");
+            panic!()
+        }
+    }
+    buffer
+}
+
+/// How `--dump-tokens` and `--dump-ast` print
+#[derive(Clone, Copy, PartialEq)]
+enum DumpFormat
 {
-    let tokens = tokenise(contents.as_ref());
-    let nodes = parse(tokens);
-    nodes
+    /// Readable, one token or node per line
+    Text,
+    /// `=json`, for tooling
+    Json,
+}
+
+enum Prelude
+{
+    /// `_wrapper_file.c` and `_start.c`, or nothing when linking with libc
+    Builtin,
+    /// `-ffreestanding`, only the entry point in `_start.c` so the program still runs
+    Freestanding,
+    /// `-nostdlib`, nothing is prepended and the program provides its own `_start`
+    Empty,
+    /// `--prelude <file>`
+    File(String),
+}
+
+struct Options
+{
+    input : Option<String>,
+    prelude : Prelude,
+    /// Links through the system C runtime, which calls main with argc, argv and envp and runs
+    /// the atexit handlers that flush stdio
+    libc : bool,
+    /// Doesn't link libc at all, set by `-nostdlib` and `-ffreestanding`
+    nostdlib : bool,
+    assemble_only : bool,
+    /// `-g`, emits DWARF so gdb can step through the source and print variables
+    debug_info : bool,
+    /// `--annotate-asm`, puts each source line in test.asm as a comment
+    annotate_asm : bool,
+    /// `--dump-tokens`, prints the preprocessed tokens of the input instead of compiling it
+    dump_tokens : Option<DumpFormat>,
+    /// `--dump-ast`, prints the typed syntax tree of the input instead of compiling it
+    dump_ast : Option<DumpFormat>,
+}
+
+impl Options
+{
+    fn from_args(args : impl Iterator<Item = String>) -> Self
+    {
+        let mut options = Options { input: None, prelude: Prelude::Builtin, libc: false, nostdlib: false, assemble_only: false, debug_info: false, annotate_asm: false, dump_tokens: None, dump_ast: None };
+
+        let mut args = args.skip(1);
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "--assemble" => options.assemble_only = true,
+                "--libc" => options.libc = true,
+                "-g" => options.debug_info = true,
+                "--annotate-asm" => options.annotate_asm = true,
+                "--dump-tokens" => options.dump_tokens = Some(DumpFormat::Text),
+                "--dump-tokens=json" => options.dump_tokens = Some(DumpFormat::Json),
+                "--dump-ast" => options.dump_ast = Some(DumpFormat::Text),
+                "--dump-ast=json" => options.dump_ast = Some(DumpFormat::Json),
+                "-ffreestanding" => {
+                    options.prelude = Prelude::Freestanding;
+                    options.nostdlib = true;
+                }
+                "-nostdlib" => {
+                    options.prelude = Prelude::Empty;
+                    options.nostdlib = true;
+                }
+                "--prelude" => match args.next()
+                {
+                    Some(path) => options.prelude = Prelude::File(path),
+                    None => {
+                        eprintln!("Error: --prelude expects a file");
+                        panic!()
+                    }
+                },
+                _ if arg.starts_with('-') => {
+                    eprintln!("Error: Unknown option {arg}");
+                    panic!()
+                }
+                _ => options.input = Some(arg),
+            }
+        }
+
+        options
+    }
+
+    /// The preprocessor for the input, which can only use the C library the prelude provides
+    fn preprocessor(&self) -> Preprocessor
+    {
+        match self.prelude
+        {
+            Prelude::Freestanding | Prelude::Empty => Preprocessor::freestanding(),
+            _ => Preprocessor::new(),
+        }
+    }
+
+    fn prelude(&self) -> &'static (Vec<ASTNode>, Sources)
+    {
+        PRELUDE.get_or_init(|| match &self.prelude
+        {
+            Prelude::Builtin if self.libc => (vec![], Sources::new()),
+            Prelude::Builtin => {
+                let (mut nodes, mut sources) = parse_file(Preprocessor::new(), C_WRAPPER, "_wrapper_file.c", None);
+                let (mut start, start_sources) = parse_file(Preprocessor::new(), C_START, "_start.c", None);
+                nodes.append(&mut start);
+                sources.extend(start_sources);
+                (nodes, sources)
+            }
+            Prelude::Freestanding => parse_file(Preprocessor::new(), C_START, "_start.c", None),
+            Prelude::Empty => (vec![], Sources::new()),
+            Prelude::File(path) => parse_file(Preprocessor::new(), read_file(path), path, Path::new(path).parent()),
+        })
+    }
 }
 
 fn main()
 {
-    let arg = env::args().collect::<Vec<String>>();
-    if arg.contains(&"--assemble".to_string())
+    let options = Options::from_args(env::args());
+
+    if options.dump_tokens.is_some() || options.dump_ast.is_some()
+    {
+        dump(&options);
+        return;
+    }
+
+    if options.assemble_only
     {
         println!("Only Assembling");
-        assemble();
+        assemble(&options);
     } else {
-        let mut wrapper = parse_file(C_WRAPPER);
-        let mut buffer = String::new();
-        File::open(env::args().nth(1).unwrap()).unwrap().read_to_string(&mut buffer).unwrap();
-        let mut main_file = parse_file(buffer);
+        let Some(input) = &options.input else {
+            eprintln!("Error: No input file");
+            panic!()
+        };
+
+        let (mut wrapper, mut sources) = options.prelude().clone();
+        let (mut main_file, main_sources) = parse_file(options.preprocessor(), read_file(input), input, Path::new(input).parent());
         wrapper.append(&mut main_file);
-        let asm = compile(wrapper);
-    
+        sources.extend(main_sources);
+
+        let compile_options = CompileOptions { debug_info: options.debug_info, annotate_asm: options.annotate_asm, sources, file: input.clone() };
+        let asm = compile_with_options(wrapper, compile_options);
+
         OpenOptions::new().read(true).write(true).truncate(true).create(true).open("test.asm").unwrap().write(asm.as_bytes()).unwrap();
-        assemble();
+        assemble(&options);
     }
     println!("Finished Compilation!");
 }
 
-pub fn assemble()
+/// Prints what `--dump-tokens` and `--dump-ast` ask for, of the input file only
+fn dump(options : &Options)
+{
+    let Some(input) = &options.input else {
+        eprintln!("Error: No input file");
+        panic!()
+    };
+    let contents = read_file(input);
+
+    if let Some(format) = options.dump_tokens
+    {
+        let (tokens, locations) = options.preprocessor().preprocess(&contents, input, Path::new(input).parent());
+        match format
+        {
+            DumpFormat::Text => print!("{}", dump::render_tokens(&tokens, &locations)),
+            DumpFormat::Json => print!("{}", dump::render_tokens_json(&tokens, &locations)),
+        }
+    }
+
+    if let Some(format) = options.dump_ast
+    {
+        // The prelude is only used to type calls to the functions it declares
+        let (prelude, _) = options.prelude();
+        let (ast, _) = parse_file(options.preprocessor(), &contents, input, Path::new(input).parent());
+        let tree = dump::ast_tree(prelude, &ast);
+        match format
+        {
+            DumpFormat::Text => print!("{}", dump::render_tree(&tree)),
+            DumpFormat::Json => print!("{}", dump::render_json(&tree)),
+        }
+    }
+}
+
+fn assemble(options : &Options)
 {
     if cfg!(target_os = "linux")
     {
         Command::new("nasm").args(["-f", "elf64", "test.asm", "-o", "test.o"]).spawn().unwrap().wait().unwrap();
-        Command::new("ld").args(["test.o", "-lc", "-I", "/lib64/ld-linux-x86-64.so.2", "-o", "test"]).spawn().unwrap().wait().unwrap();
+        if options.libc
+        {
+            // cc adds crt1.o, crti.o and crtn.o, the output isn't position independent
+            Command::new("cc").args(["-no-pie", "test.o", "-o", "test"]).spawn().unwrap().wait().unwrap();
+        } else if options.nostdlib
+        {
+            Command::new("ld").args(["test.o", "-o", "test"]).spawn().unwrap().wait().unwrap();
+        } else
+        {
+            Command::new("ld").args(["test.o", "-lc", "--dynamic-linker", "/lib64/ld-linux-x86-64.so.2", "-o", "test"]).spawn().unwrap().wait().unwrap();
+        }
         Command::new("rm").args(["test.o", /*"test.asm"*/]).spawn().unwrap().wait().unwrap();
     }
-}
\ No newline at end of file
+}
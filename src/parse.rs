@@ -1,66 +1,529 @@
-use std::{cell::Ref, collections::HashMap, iter::Peekable, slice::Iter, u32};
+use std::{cell::Ref, collections::HashMap, slice::Iter, u32};
 
 use low_level_ir::{CompareOperation, ComparePredicate, OperandType, Size, Value};
 
-use crate::tokenise::Token;
+use crate::{fold, tokenise::{IntSuffix, Location, Token}};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     VOID,
-    INT,
+    BOOL,
     CHAR,
+    UCHAR,
+    SHORT,
+    USHORT,
+    INT,
+    UINT,
+    LONG,
+    ULONG,
+    LONGLONG,
+    ULONGLONG,
+    FLOAT,
+    DOUBLE,
     PTR(Box<Type>),
+    ARRAY(Box<Type>, u32),
+    /// Return type, parameter types and whether the parameters end in `...`
+    FUNC(Box<Type>, Vec<Type>, bool),
+    CONST(Box<Type>),
+    VOLATILE(Box<Type>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StorageClass {
+    Auto,
+    /// Internal linkage at file scope, persistent storage for locals
+    Static,
+    Extern,
+}
+
+/// The result of reading a declarator, e.g. the `(*fp)(int, char)` in `int (*fp)(int, char)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declarator {
+    pub name: Option<String>,
+    pub ty: Type,
+    /// Named parameters of the function suffix applied directly to `name`, used by definitions
+    pub parameters: Vec<(String, Type)>,
+    pub variadic: bool,
 }
 
 impl Type {
     pub fn size(&self) -> Size {
         match self {
-            Type::VOID => panic!(),
-            Type::INT => Size::DoubleWord,
-            Type::CHAR => Size::Byte,
+            Type::CONST(inner) | Type::VOLATILE(inner) => inner.size(),
+            Type::VOID | Type::ARRAY(_, _) | Type::FUNC(_, _, _) => panic!(),
+            Type::BOOL | Type::CHAR | Type::UCHAR => Size::Byte,
+            Type::SHORT | Type::USHORT => Size::Word,
+            Type::INT | Type::UINT => Size::DoubleWord,
+            Type::LONG | Type::ULONG | Type::LONGLONG | Type::ULONGLONG => Size::QuadWord,
+            Type::FLOAT => Size::DoubleWord,
+            Type::DOUBLE => Size::QuadWord,
             Type::PTR(_) => Size::QuadWord,
         }
     }
 
+    pub fn bytes(&self) -> u32 {
+        match self {
+            Type::ARRAY(inner, count) => inner.bytes() * count,
+            Type::CONST(inner) | Type::VOLATILE(inner) => inner.bytes(),
+            Type::VOID | Type::FUNC(_, _, _) => {
+                eprintln!("Error: {self:?} has no size");
+                panic!()
+            }
+            _ => match self.size() {
+                Size::Byte => 1,
+                Size::Word => 2,
+                Size::DoubleWord => 4,
+                Size::QuadWord => 8,
+            },
+        }
+    }
+
+    /// Qualifiers don't change the representation, the IR reloads every variable from memory on
+    /// each access so volatile objects are never cached in registers
     pub fn into_ir(&self) -> OperandType {
         match self {
-            Type::VOID => OperandType::Undefined,
+            Type::CONST(inner) | Type::VOLATILE(inner) => inner.into_ir(),
+            Type::VOID | Type::FUNC(_, _, _) => OperandType::Undefined,
             Type::CHAR => OperandType::Char,
-            Type::INT => OperandType::Int(self.size()),
+            Type::SHORT | Type::INT | Type::LONG | Type::LONGLONG => OperandType::Int(self.size()),
+            Type::BOOL | Type::UCHAR | Type::USHORT | Type::UINT | Type::ULONG | Type::ULONGLONG => {
+                OperandType::UInt(self.size())
+            }
+            Type::FLOAT | Type::DOUBLE => OperandType::Float(self.size()),
             Type::PTR(a) => OperandType::Pointer(Box::new(a.into_ir())),
+            Type::ARRAY(a, count) => OperandType::Array(Box::new(a.into_ir()), *count),
+        }
+    }
+
+    /// The type without any top level `const` or `volatile`
+    pub fn unqualified(&self) -> &Type {
+        match self {
+            Type::CONST(inner) | Type::VOLATILE(inner) => inner.unqualified(),
+            _ => self,
+        }
+    }
 
+    pub fn is_const(&self) -> bool {
+        match self {
+            Type::CONST(_) => true,
+            // An array of const elements is itself read only
+            Type::VOLATILE(inner) | Type::ARRAY(inner, _) => inner.is_const(),
+            _ => false,
         }
     }
-    
-    pub fn read_type(token: &Token, tokens: &mut Peekable<Iter<Token>>) -> Type
+
+    pub fn is_volatile(&self) -> bool {
+        match self {
+            Type::VOLATILE(_) => true,
+            Type::CONST(inner) => inner.is_volatile(),
+            _ => false,
+        }
+    }
+
+    /// Applies qualifiers, always in the order `VOLATILE(CONST(..))` so equal types compare equal
+    pub fn qualify(self, is_const: bool, is_volatile: bool) -> Type {
+        let is_const = is_const || self.is_const();
+        let is_volatile = is_volatile || self.is_volatile();
+
+        let mut ty = self.unqualified().clone();
+        if is_const {
+            ty = Type::CONST(Box::new(ty));
+        }
+        if is_volatile {
+            ty = Type::VOLATILE(Box::new(ty));
+        }
+        ty
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self.unqualified(),
+            Type::BOOL
+                | Type::CHAR
+                | Type::UCHAR
+                | Type::SHORT
+                | Type::USHORT
+                | Type::INT
+                | Type::UINT
+                | Type::LONG
+                | Type::ULONG
+                | Type::LONGLONG
+                | Type::ULONGLONG
+        )
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self.unqualified(),
+            Type::BOOL | Type::UCHAR | Type::USHORT | Type::UINT | Type::ULONG | Type::ULONGLONG
+        )
+    }
+
+    pub fn is_floating(&self) -> bool {
+        matches!(self.unqualified(), Type::FLOAT | Type::DOUBLE)
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        self.is_integer() || self.is_floating()
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(self.unqualified(), Type::PTR(_))
+    }
+
+    /// Integer conversion rank, see C11 6.3.1.1
+    pub fn rank(&self) -> u8 {
+        match self.unqualified() {
+            Type::BOOL => 0,
+            Type::CHAR | Type::UCHAR => 1,
+            Type::SHORT | Type::USHORT => 2,
+            Type::INT | Type::UINT => 3,
+            Type::LONG | Type::ULONG => 4,
+            Type::LONGLONG | Type::ULONGLONG => 5,
+            _ => {
+                eprintln!("Error: {self:?} is not an integer type");
+                panic!()
+            }
+        }
+    }
+
+    pub fn to_unsigned(&self) -> Type {
+        match self.unqualified() {
+            Type::CHAR => Type::UCHAR,
+            Type::SHORT => Type::USHORT,
+            Type::INT => Type::UINT,
+            Type::LONG => Type::ULONG,
+            Type::LONGLONG => Type::ULONGLONG,
+            _ => self.clone(),
+        }
+    }
+
+    /// Integer promotion, everything narrower than `int` is promoted to `int`
+    pub fn promote(&self) -> Type {
+        if self.is_integer() && self.rank() < Type::INT.rank() {
+            Type::INT
+        } else {
+            self.unqualified().clone()
+        }
+    }
+
+    /// The usual arithmetic conversions, see C11 6.3.1.8
+    pub fn common(&self, other: &Type) -> Type {
+        if *self.unqualified() == Type::DOUBLE || *other.unqualified() == Type::DOUBLE {
+            return Type::DOUBLE;
+        }
+
+        if *self.unqualified() == Type::FLOAT || *other.unqualified() == Type::FLOAT {
+            return Type::FLOAT;
+        }
+
+        let lhs = self.promote();
+        let rhs = other.promote();
+
+        if lhs == rhs {
+            return lhs;
+        }
+
+        if lhs.is_unsigned() == rhs.is_unsigned() {
+            return if lhs.rank() >= rhs.rank() { lhs } else { rhs };
+        }
+
+        let (unsigned, signed) = if lhs.is_unsigned() { (lhs, rhs) } else { (rhs, lhs) };
+
+        if unsigned.rank() >= signed.rank() {
+            unsigned
+        } else if signed.bytes() > unsigned.bytes() {
+            signed
+        } else {
+            signed.to_unsigned()
+        }
+    }
+
+    /// Alignment in bytes, see `_Alignof`
+    pub fn align(&self) -> u32 {
+        match self.unqualified() {
+            Type::ARRAY(inner, _) => inner.align(),
+            _ => self.bytes(),
+        }
+    }
+
+    pub fn is_function(&self) -> bool {
+        matches!(self.unqualified(), Type::FUNC(_, _, _))
+    }
+
+    pub fn is_function_pointer(&self) -> bool {
+        matches!(self.unqualified(), Type::PTR(inner) if inner.is_function())
+    }
+
+    /// Parameters of array and function type are adjusted to pointers
+    pub fn decay(self) -> Type {
+        match self {
+            Type::ARRAY(inner, _) => Type::PTR(inner),
+            Type::FUNC(_, _, _) => Type::PTR(Box::new(self)),
+            _ => self,
+        }
+    }
+
+    pub fn read_type(token: &Token, tokens: &mut Tokens) -> Type
+    {
+        let base_type = Self::read_base_type(token, tokens);
+
+        Self::read_declarator(base_type, tokens).ty
+    }
+
+    pub fn is_type_specifier(token: &Token) -> bool
+    {
+        matches!(token, Token::Keyword(value) if matches!(value.as_str(), "void" | "_Bool" | "bool" | "char" | "short" | "int" | "long" | "signed" | "unsigned" | "float" | "double" | "const" | "volatile" | "va_list"))
+    }
+
+    /// Reads a list of type specifiers such as `unsigned long long int`
+    pub fn read_base_type(token: &Token, tokens: &mut Tokens) -> Type
     {
-        let base_type = if let Token::Keyword(value) = token
+        if !Self::is_type_specifier(token)
         {
-            Self::from(value)
-        } else {
             eprintln!("Expected a type, got {token} instead");
             panic!()
+        }
+
+        let mut specifiers = vec![token.extract_keyword().unwrap()];
+        while Self::is_type_specifier(tokens.peek().unwrap())
+        {
+            specifiers.push(tokens.next().unwrap().extract_keyword().unwrap());
+        }
+
+        // Qualifiers may appear anywhere among the specifiers, `int const` is `const int`
+        let is_const = specifiers.iter().any(|s| s == "const");
+        let is_volatile = specifiers.iter().any(|s| s == "volatile");
+        specifiers.retain(|s| s != "const" && s != "volatile");
+
+        Self::read_specifiers(specifiers).qualify(is_const, is_volatile)
+    }
+
+    fn read_specifiers(specifiers: Vec<String>) -> Type
+    {
+        let count = |name: &str| specifiers.iter().filter(|s| s.as_str() == name).count();
+        let unsigned = count("unsigned") > 0;
+
+        if count("unsigned") + count("signed") > 1
+        {
+            eprintln!("Error: Conflicting signedness in {}", specifiers.join(" "));
+            panic!()
+        }
+
+        if count("float") + count("double") + count("_Bool") + count("bool") + count("va_list") > 0
+        {
+            return match specifiers.iter().map(|s| s.as_str()).collect::<Vec<&str>>().as_slice() {
+                ["float"] => Type::FLOAT,
+                ["double"] => Type::DOUBLE,
+                ["_Bool"] | ["bool"] => Type::BOOL,
+                // The System V `va_list` is an array of one { gp_offset, fp_offset,
+                // overflow_arg_area, reg_save_area } struct, both offsets sharing the first word
+                ["va_list"] => Type::ARRAY(Box::new(Type::ULONG), 3),
+                _ => {
+                    eprintln!("Error: {} is not a supported type", specifiers.join(" "));
+                    panic!()
+                }
+            };
+        }
+
+        let base_type = match (count("void"), count("char"), count("short"), count("long")) {
+            (1, 0, 0, 0) if specifiers.len() == 1 => Type::VOID,
+            (0, 1, 0, 0) if count("int") == 0 => Type::CHAR,
+            (0, 0, 1, 0) => Type::SHORT,
+            (0, 0, 0, 1) => Type::LONG,
+            (0, 0, 0, 2) => Type::LONGLONG,
+            (0, 0, 0, 0) if count("int") <= 1 => Type::INT,
+            _ => {
+                eprintln!("Error: {} is not a valid type", specifiers.join(" "));
+                panic!()
+            }
+        };
+
+        if unsigned { base_type.to_unsigned() } else { base_type }
+    }
+
+    /// declarator := '*'* direct-declarator
+    /// direct-declarator := [ identifier | '(' declarator ')' ] { '[' int ']' | '(' parameters ')' }
+    pub fn read_declarator(base_type: Type, tokens: &mut Tokens) -> Declarator
+    {
+        let mut ty = base_type;
+        while **tokens.peek().unwrap() == Token::Punctuation('*')
+        {
+            tokens.next();
+            ty = Type::PTR(Box::new(ty));
+
+            // `char* const p` is a constant pointer to char
+            loop
+            {
+                match tokens.peek().and_then(|tk| tk.extract_keyword()).as_deref()
+                {
+                    Some("const") => ty = ty.qualify(true, false),
+                    Some("volatile") => ty = ty.qualify(false, true),
+                    _ => break,
+                }
+                tokens.next();
+            }
+        }
+
+        let is_nested = if **tokens.peek().unwrap() == Token::Punctuation('(')
+        {
+            let mut lookahead = tokens.clone();
+            lookahead.next();
+            matches!(lookahead.peek(), Some(Token::Punctuation('*' | '(' | '[')) | Some(Token::StringLiteral(_)))
+        } else {
+            false
         };
 
-        if let Token::Punctuation(punc) = **tokens.peek().unwrap()
+        if is_nested
         {
-            if punc == '*'
+            // The suffixes bind tighter than the parenthesised declarator, so read them first
+            // and then apply the inner declarator on top of the resulting type
+            let mut inner = tokens.clone();
+            inner.next();
+
+            let mut depth = 0;
+            loop
             {
+                match tokens.next().expect("UNEXPECTED EOF")
+                {
+                    Token::Punctuation('(') => depth += 1,
+                    Token::Punctuation(')') => {
+                        depth -= 1;
+                        if depth == 0 { break; }
+                    }
+                    _ => {}
+                }
+            }
+
+            let (ty, _, _) = Self::read_declarator_suffixes(ty, tokens);
+            let declarator = Self::read_declarator(ty, &mut inner);
+            assert_eq!(*inner.next().unwrap(), Token::Punctuation(')'));
+            return declarator;
+        }
+
+        let name = match tokens.peek() {
+            Some(Token::StringLiteral(name)) => {
                 tokens.next();
-                return Type::PTR(Box::new(base_type));
+                Some(name.clone())
+            }
+            _ => None,
+        };
+
+        let (ty, parameters, variadic) = Self::read_declarator_suffixes(ty, tokens);
+
+        Declarator { name, ty, parameters, variadic }
+    }
+
+    fn read_declarator_suffixes(base_type: Type, tokens: &mut Tokens) -> (Type, Vec<(String, Type)>, bool)
+    {
+        // `int a[2][3]` is an array of 2 arrays of 3 ints, so suffixes are applied right to left
+        let mut suffixes = vec![];
+        let mut parameters = None;
+
+        loop
+        {
+            match **tokens.peek().unwrap()
+            {
+                Token::Punctuation('[') => {
+                    tokens.next();
+                    // The size can be any integer constant expression, such as `[4 * sizeof(int)]`
+                    let size = parse_conditional(tokens.next().unwrap(), tokens);
+                    let count = match fold::evaluate(&size) {
+                        Some(count) if count.value > 0 => count.value as u32,
+                        _ => {
+                            eprintln!("Error: Array size {size:?} is not a positive integer constant");
+                            panic!()
+                        }
+                    };
+                    assert_eq!(*tokens.next().unwrap(), Token::Punctuation(']'));
+                    suffixes.push(Err(count));
+                }
+                Token::Punctuation('(') => {
+                    tokens.next();
+                    let (params, variadic) = read_parameters(tokens);
+                    suffixes.push(Ok((params.iter().map(|(_, ty)| ty.clone()).collect(), variadic)));
+                    if parameters.is_none()
+                    {
+                        parameters = Some((params, variadic));
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        let mut ty = base_type;
+        for suffix in suffixes.into_iter().rev()
+        {
+            ty = match suffix {
+                Ok((params, variadic)) => Type::FUNC(Box::new(ty), params, variadic),
+                Err(count) => Type::ARRAY(Box::new(ty), count),
+            };
+        }
+
+        let (parameters, variadic) = parameters.unwrap_or_default();
+        (ty, parameters, variadic)
+    }
+}
+
+/// Reads a parameter list up to and including the closing `)`, and whether it ends in `...`
+fn read_parameters(tokens: &mut Tokens) -> (Vec<(String, Type)>, bool)
+{
+    let mut parameters = vec![];
+    let mut variadic = false;
+
+    // `(void)` declares no parameters
+    if **tokens.peek().unwrap() == Token::Keyword("void".to_string())
+    {
+        let mut lookahead = tokens.clone();
+        lookahead.next();
+        if lookahead.peek() == Some(&&Token::Punctuation(')'))
+        {
+            tokens.next();
+        }
+    }
+
+    while **tokens.peek().expect("UNEXPECTED EOF") != Token::Punctuation(')') {
+        if **tokens.peek().unwrap() == Token::Ellipsis
+        {
+            if parameters.is_empty()
+            {
+                eprintln!("Error: A variadic function needs at least one named parameter");
+                panic!()
             }
+            tokens.next();
+            variadic = true;
+            break;
         }
 
-        base_type
+        let base_type = Type::read_base_type(tokens.next().unwrap(), tokens);
+
+        let declarator = Type::read_declarator(base_type, tokens);
+        parameters.push((declarator.name.unwrap_or_default(), declarator.ty.decay()));
+
+        if **tokens.peek().unwrap() != Token::Punctuation(',')
+        {
+            break;
+        }
+        tokens.next();
     }
+
+    assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
+
+    (parameters, variadic)
 }
 
 impl From<&String> for Type {
     fn from(value: &String) -> Self {
         match value.as_str() {
             "void" => Self::VOID,
-            "int" => Self::INT,
+            "int" | "signed" => Self::INT,
             "char" => Self::CHAR,
+            "short" => Self::SHORT,
+            "long" => Self::LONG,
+            "unsigned" => Self::UINT,
+            "float" => Self::FLOAT,
+            "double" => Self::DOUBLE,
+            "_Bool" | "bool" => Self::BOOL,
             _ => {
                 eprintln!("Error: {value} is not a valid type");
                 panic!()
@@ -75,9 +538,12 @@ pub enum ASTValue {
     Ref(String),
     StringLiteral(String),
     StringValue(String),
-    IntValue(i32),
+    IntValue(i64),
+    FloatValue(f64),
     CharValue(char),
     FunctionCall(String, Vec<ASTNode>),
+    /// A call through an expression, e.g. `(*fp)(1, 'a')`
+    IndirectCall(Box<ASTNode>, Vec<ASTNode>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -88,128 +554,522 @@ pub struct Comparison
     pub operation : CompareOperation
 }
 
+/// The tokens being parsed, along with where each came from when that is known
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    tokens: Iter<'a, Token>,
+    /// One per token, or empty
+    locations: &'a [Location],
+    /// The token after the last one taken, kept so `peek` can hand out a reference to it
+    peeked: Option<&'a Token>,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(tokens: &'a [Token], locations: &'a [Location]) -> Self {
+        Self { tokens: tokens.iter(), locations, peeked: tokens.first() }
+    }
+
+    pub fn peek(&self) -> Option<&&'a Token> {
+        self.peeked.as_ref()
+    }
+
+    /// Where the token last taken came from
+    fn location(&self) -> Option<&'a Location> {
+        self.locations.get(self.locations.len().checked_sub(self.tokens.len() + 1)?)
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = &'a Token;
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.next();
+        self.peeked = self.tokens.as_slice().first();
+        token
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ASTNode {
-    FunctionDeclaration(Type, String, Vec<ASTNode>, Vec<(String, Type)>),
+    /// Return type, name, body, parameters, whether the parameters end in `...` and storage
+    FunctionDeclaration(Type, String, Vec<ASTNode>, Vec<(String, Type)>, bool, StorageClass),
+    /// A declaration of a function without a body, holding the function's type
+    FunctionPrototype(Type, String, StorageClass),
+    /// Marks the statement after it as coming from this file and line, for debug info
+    SourceLine(String, u32),
     FunctionCall(String, Vec<ASTNode>),
+    IndirectCall(Box<ASTNode>, Vec<ASTNode>),
     Add(Box<ASTNode>, Box<ASTNode>),
     Sub(Box<ASTNode>, Box<ASTNode>),
-    VariableDeclaration(Type, String, Box<ASTNode>),
-    SetVariable(ASTValue, Box<ASTNode>),
+    Mul(Box<ASTNode>, Box<ASTNode>),
+    Div(Box<ASTNode>, Box<ASTNode>),
+    Mod(Box<ASTNode>, Box<ASTNode>),
+    BitAnd(Box<ASTNode>, Box<ASTNode>),
+    BitOr(Box<ASTNode>, Box<ASTNode>),
+    BitXor(Box<ASTNode>, Box<ASTNode>),
+    BitNot(Box<ASTNode>),
+    ShiftLeft(Box<ASTNode>, Box<ASTNode>),
+    ShiftRight(Box<ASTNode>, Box<ASTNode>),
+    Compare(Comparison),
+    And(Box<ASTNode>, Box<ASTNode>),
+    Or(Box<ASTNode>, Box<ASTNode>),
+    Not(Box<ASTNode>),
+    /// Dereference of an arbitrary pointer expression, `*name` is kept as `ASTValue::Deref`
+    Deref(Box<ASTNode>),
+    /// `++` and `--`, `postfix` yields the value from before the update
+    Increment { lvalue: Box<ASTNode>, decrement: bool, postfix: bool },
+    VariableDeclaration(Type, String, Option<Box<ASTNode>>, StorageClass),
+    SetVariable(Box<ASTNode>, Box<ASTNode>),
+    /// `lhs op= rhs`, holding the symbol of the binary operator
+    CompoundAssignment(Box<ASTNode>, String, Box<ASTNode>),
+    /// `(type)expr`
+    Cast(Type, Box<ASTNode>),
+    /// `sizeof expr`, the operand is never evaluated
+    SizeOf(Box<ASTNode>),
+    SizeOfType(Type),
+    AlignOfType(Type),
+    /// `va_start(list, last)`, the last named parameter is only checked
+    VaStart(Box<ASTNode>),
+    /// `va_arg(list, type)`
+    VaArg(Box<ASTNode>, Type),
+    /// `va_end(list)`, which has nothing to clean up
+    VaEnd(Box<ASTNode>),
+    /// `predicate ? main : other`, only one of the arms is evaluated
+    Ternary(Box<ASTNode>, Box<ASTNode>, Box<ASTNode>),
+    /// `lhs, rhs`, evaluates `lhs` for its side effects and yields `rhs`
+    Comma(Box<ASTNode>, Box<ASTNode>),
     InlineAssembly(String),
+    /// `asm volatile ("template" : outputs : inputs : clobbers)`, each operand being a
+    /// constraint such as `"=r"` and the C expression bound to it
+    ExtendedAssembly { template: String, outputs: Vec<(String, ASTNode)>, inputs: Vec<(String, ASTNode)>, clobbers: Vec<String> },
     Return(Option<Box<ASTNode>>),
     Value(ASTValue),
-    If { predicate : Comparison, main_body : Vec<ASTNode>, else_body : Option<Vec<ASTNode>> }
+    If { predicate : Box<ASTNode>, main_body : Vec<ASTNode>, else_body : Option<Vec<ASTNode>> }
+}
+
+/// Applies any postfix `++` and `--` following a value
+fn parse_postfix(value: ASTNode, tokens: &mut Tokens) -> ASTNode
+{
+    let mut value = value;
+
+    loop
+    {
+        let decrement = match tokens.peek() {
+            Some(Token::MathSymbol(x)) if x == "++" => false,
+            Some(Token::MathSymbol(x)) if x == "--" => true,
+            _ => break,
+        };
+        tokens.next();
+
+        value = ASTNode::Increment { lvalue: Box::new(value), decrement, postfix: true };
+    }
+
+    value
 }
 
-fn _try_set_value(lhs : &ASTValue, token: &Token, tokens: &mut Peekable<Iter<Token>>) -> Option<ASTNode>
+/// Reads a call's arguments up to and including the closing `)`
+fn parse_arguments(tokens: &mut Tokens) -> Vec<ASTNode>
 {
-    if **tokens.peek().unwrap() == Token::Punctuation('=') {
-        assert_eq!(*tokens.next().unwrap(), Token::Punctuation('='));
-        let value = _parse(tokens.next().unwrap(), tokens, true);
-        assert_eq!(*tokens.next().unwrap(), Token::Punctuation(';'));
+    let mut parameters = vec![];
+    while **tokens.peek().expect("UNEXPECTED EOF") != Token::Punctuation(')') {
+        if **tokens.peek().unwrap() == Token::Punctuation(',') {
+            tokens.next();
+        }
+        let value = parse_expression(tokens.next().unwrap(), tokens);
+        parameters.push(value);
+    }
+
+    assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
+    parameters
+}
 
-        return Some(ASTNode::SetVariable(lhs.clone(), Box::new(value.unwrap())));
+/// Binding power of a binary operator, higher binds tighter
+fn binary_precedence(token: &Token) -> Option<u8>
+{
+    match token
+    {
+        Token::Punctuation('*') => Some(10),
+        Token::MathSymbol(x) if matches!(x.as_str(), "/" | "%") => Some(10),
+        Token::MathSymbol(x) if matches!(x.as_str(), "+" | "-") => Some(9),
+        Token::MathSymbol(x) if matches!(x.as_str(), "<<" | ">>") => Some(8),
+        Token::MathSymbol(x) if matches!(x.as_str(), "<" | ">" | "<=" | ">=") => Some(7),
+        Token::MathSymbol(x) if matches!(x.as_str(), "==" | "!=") => Some(6),
+        // In an infix position & is always bitwise and, address-of is handled by _parse
+        Token::Punctuation('&') => Some(5),
+        Token::MathSymbol(x) if x == "^" => Some(4),
+        Token::MathSymbol(x) if x == "|" => Some(3),
+        Token::MathSymbol(x) if x == "&&" => Some(2),
+        Token::MathSymbol(x) if x == "||" => Some(1),
+        _ => None
     }
+}
+
+fn parse_binary(token: &Token, tokens: &mut Tokens, min_precedence: u8) -> ASTNode
+{
+    let mut lhs = _parse(token, tokens).expect("Expected a value");
+
+    while let Some(precedence) = tokens.peek().and_then(|tk| binary_precedence(tk))
+    {
+        if precedence < min_precedence
+        {
+            break;
+        }
+
+        let operator = match tokens.next().unwrap()
+        {
+            Token::Punctuation(x) => x.to_string(),
+            Token::MathSymbol(x) => x.clone(),
+            _ => unreachable!()
+        };
+        let rhs = parse_binary(tokens.next().unwrap(), tokens, precedence + 1);
 
-    None
+        lhs = binary_node(&operator, lhs, rhs);
+    }
+
+    lhs
+}
+
+/// Builds the node for a binary operator from its symbol, also used for compound assignments
+pub fn binary_node(operator: &str, lhs: ASTNode, rhs: ASTNode) -> ASTNode
+{
+    let lhs = Box::new(lhs);
+    let rhs = Box::new(rhs);
+
+    match operator
+    {
+        "*" => ASTNode::Mul(lhs, rhs),
+        "&" => ASTNode::BitAnd(lhs, rhs),
+        "<<" => ASTNode::ShiftLeft(lhs, rhs),
+        ">>" => ASTNode::ShiftRight(lhs, rhs),
+        "^" => ASTNode::BitXor(lhs, rhs),
+        "|" => ASTNode::BitOr(lhs, rhs),
+        "/" => ASTNode::Div(lhs, rhs),
+        "%" => ASTNode::Mod(lhs, rhs),
+        "+" => ASTNode::Add(lhs, rhs),
+        "-" => ASTNode::Sub(lhs, rhs),
+        "&&" => ASTNode::And(lhs, rhs),
+        "||" => ASTNode::Or(lhs, rhs),
+        x => ASTNode::Compare(Comparison { lhs, rhs, operation: comparison_operation(x) }),
+    }
+}
+
+fn parse_conditional(token: &Token, tokens: &mut Tokens) -> ASTNode
+{
+    let predicate = parse_binary(token, tokens, 0);
+
+    if **tokens.peek().unwrap() != Token::Punctuation('?')
+    {
+        return predicate;
+    }
+    tokens.next();
+
+    let main = parse_comma_expression(tokens.next().unwrap(), tokens);
+    assert_eq!(*tokens.next().unwrap(), Token::Punctuation(':'));
+    let other = parse_conditional(tokens.next().unwrap(), tokens);
+
+    ASTNode::Ternary(Box::new(predicate), Box::new(main), Box::new(other))
+}
+
+/// Parses an assignment expression, assignments are right associative so `a = b = 0` sets `b` first
+///
+/// This doesn't include the comma operator, so it can be used for arguments and initialisers
+pub fn parse_expression(token: &Token, tokens: &mut Tokens) -> ASTNode
+{
+    let lhs = parse_conditional(token, tokens);
+
+    let operation = match tokens.peek() {
+        Some(Token::Punctuation('=')) => None,
+        Some(Token::MathSymbol(x)) if matches!(x.as_str(), "+=" | "-=" | "*=" | "/=" | "%=" | "&=" | "|=" | "^=" | "<<=" | ">>=") => {
+            Some(x.trim_end_matches('=').to_string())
+        }
+        _ => return lhs,
+    };
+    tokens.next();
+
+    let value = Box::new(parse_expression(tokens.next().unwrap(), tokens));
+
+    match operation {
+        None => ASTNode::SetVariable(Box::new(lhs), value),
+        Some(operation) => ASTNode::CompoundAssignment(Box::new(lhs), operation, value),
+    }
 }
 
-fn parse_comparison(token: &Token, tokens: &mut Peekable<Iter<Token>>) -> Option<Comparison>
+/// Parses a full expression, including the comma operator
+pub fn parse_comma_expression(token: &Token, tokens: &mut Tokens) -> ASTNode
+{
+    let mut lhs = parse_expression(token, tokens);
+
+    while **tokens.peek().unwrap() == Token::Punctuation(',')
+    {
+        tokens.next();
+        let rhs = parse_expression(tokens.next().unwrap(), tokens);
+        lhs = ASTNode::Comma(Box::new(lhs), Box::new(rhs));
+    }
+
+    lhs
+}
+
+/// Parses a statement, anything that isn't a declaration, control flow or inline assembly is an
+/// expression evaluated for its side effects. A declaration gives one node for each name it declares
+fn parse_statement(token: &Token, tokens: &mut Tokens) -> Vec<ASTNode>
 {
     match token
     {
-        Token::MathSymbol(x) if matches!(x.as_str(), ">" | "<" | "==" | ">=" | "<=" | "!=`") => {
-            let lhs = Box::new(_parse(tokens.next().unwrap(), tokens, true).unwrap());
-            let rhs = Box::new(_parse(tokens.next().unwrap(), tokens, true).unwrap());
-            let operation = match x.as_str()
+        Token::Keyword(keyword) if matches!(keyword.as_str(), "static" | "extern") || Type::is_type_specifier(token) => parse_declaration(token, tokens),
+        Token::Keyword(keyword) if !matches!(keyword.as_str(), "true" | "false" | "sizeof" | "_Alignof" | "alignof" | "va_start" | "va_arg" | "va_end") => {
+            match _parse(token, tokens)
             {
-                "==" => CompareOperation::EQ,
-                ">" => CompareOperation::GT,
-                "<" => CompareOperation::LT,
-                "<=" => CompareOperation::LTE,
-                ">=" => CompareOperation::GTE,
-                "!=" => CompareOperation::NEQ,
-                _ => panic!()
-            };
-
-            Some(Comparison { lhs, rhs, operation })
+                Some(statement) => vec![statement],
+                None => {
+                    eprintln!("Error: Expected a statement, got {token}");
+                    panic!()
+                }
+            }
         },
-        _ => None
+        Token::Punctuation('[') => vec![_parse(token, tokens).unwrap()],
+        _ => {
+            let expression = parse_comma_expression(token, tokens);
+            assert_eq!(*tokens.next().unwrap(), Token::Punctuation(';'));
+
+            match expression
+            {
+                ASTNode::Value(ASTValue::FunctionCall(name, parameters)) => vec![ASTNode::FunctionCall(name, parameters)],
+                ASTNode::Value(ASTValue::IndirectCall(callee, parameters)) => vec![ASTNode::IndirectCall(callee, parameters)],
+                expression => vec![expression],
+            }
+        }
+    }
+}
+
+/// Parses a function definition, or a declaration of one or more names sharing a base type such
+/// as `int a = 1, *b;`
+fn parse_declaration(token: &Token, tokens: &mut Tokens) -> Vec<ASTNode>
+{
+    let (storage, token) = match token.extract_keyword().as_deref() {
+        Some("static") => (StorageClass::Static, tokens.next().unwrap()),
+        Some("extern") => (StorageClass::Extern, tokens.next().unwrap()),
+        _ => (StorageClass::Auto, token),
+    };
+
+    let base_type = Type::read_base_type(token, tokens);
+    let mut declarations = vec![];
+
+    loop
+    {
+        let declarator = Type::read_declarator(base_type.clone(), tokens);
+        let ty = declarator.ty;
+        let Some(name) = declarator.name else {
+            eprintln!("Error: Expected a name in the declaration of {ty:?}");
+            panic!()
+        };
+
+        let mut next = tokens.next();
+        if next == Some(&Token::Punctuation('{')) && ty.is_function() && declarations.is_empty()
+        {
+            // Function Declaration
+            let Type::FUNC(return_type, _, _) = ty else { unreachable!() };
+
+            let mut internal_nodes = vec![];
+
+            while let Some(tk) = tokens.next() {
+                if *tk == Token::Punctuation('}') {
+                    break;
+                }
+
+                internal_nodes.extend(source_line(tokens));
+                internal_nodes.extend(parse_statement(tk, tokens));
+            }
+
+            return vec![ASTNode::FunctionDeclaration(
+                *return_type,
+                name,
+                internal_nodes,
+                declarator.parameters,
+                declarator.variadic,
+                storage,
+            )];
+        }
+
+        if next == Some(&Token::Punctuation('='))
+        {
+            // Variable Declaration
+            let value = parse_expression(tokens.next().unwrap(), tokens);
+            declarations.push(ASTNode::VariableDeclaration(ty, name.clone(), Some(Box::new(value)), storage));
+            next = tokens.next();
+        } else if ty.is_function()
+        {
+            declarations.push(ASTNode::FunctionPrototype(ty, name.clone(), storage));
+        } else
+        {
+            declarations.push(ASTNode::VariableDeclaration(ty, name.clone(), None, storage));
+        }
+
+        match next
+        {
+            Some(Token::Punctuation(',')) => {}
+            Some(Token::Punctuation(';')) => return declarations,
+            Some(token) => {
+                eprintln!("Error: Expected , or ; after the declaration of {name}, got {token}");
+                panic!()
+            }
+            None => {
+                eprintln!("Error: Expected , or ; after the declaration of {name}");
+                panic!()
+            }
+        }
     }
 }
 
-fn _parse(token: &Token, tokens: &mut Peekable<Iter<Token>>, as_value: bool) -> Option<ASTNode> {
+fn comparison_operation(symbol: &str) -> CompareOperation
+{
+    match symbol
+    {
+        "==" => CompareOperation::EQ,
+        ">" => CompareOperation::GT,
+        "<" => CompareOperation::LT,
+        "<=" => CompareOperation::LTE,
+        ">=" => CompareOperation::GTE,
+        "!=" => CompareOperation::NEQ,
+        _ => panic!()
+    }
+}
+
+/// An integer literal gets the first of its possible types that can hold it, which is
+/// written as a cast when it isn't the type `fold::literal_type` would give the plain value
+fn int_literal(value: u64, suffix: IntSuffix) -> ASTNode {
+    let ty = match (suffix.unsigned, suffix.long) {
+        (true, false) if value <= u32::MAX as u64 => Type::UINT,
+        (true, _) => Type::ULONG,
+        (false, false) if value <= i32::MAX as u64 => Type::INT,
+        (false, _) if value <= i64::MAX as u64 => Type::LONG,
+        // Too large for long, so it is unsigned like GCC makes it
+        (false, _) => Type::ULONG,
+    };
+
+    let literal = ASTNode::Value(ASTValue::IntValue(value as i64));
+    if value <= i64::MAX as u64 && fold::literal_type(value as i64) == ty {
+        literal
+    } else {
+        ASTNode::Cast(ty, Box::new(literal))
+    }
+}
+
+/// Appends an adjacent string literal, whose escapes are kept as written. A leading hex digit is
+/// written as an octal escape, so it can't extend an escape at the end of `string`
+fn concatenate(string: &mut String, next: &str) {
+    let mut chars = next.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_hexdigit() => {
+            string.push_str(&format!("\\{:03o}", c as u8));
+            string.push_str(chars.as_str());
+        }
+        _ => string.push_str(next),
+    }
+}
+
+fn _parse(token: &Token, tokens: &mut Tokens) -> Option<ASTNode> {
     match token {
         Token::CharValue(val) => {
             Some(ASTNode::Value(ASTValue::CharValue(*val)))
         }
         Token::StringValue(string) => {
-            Some(ASTNode::Value(ASTValue::StringValue(string.clone())))
+            // Adjacent string literals are concatenated, `"abc" "def"` is `"abcdef"`
+            let mut string = string.clone();
+            while let Some(Token::StringValue(next)) = tokens.peek()
+            {
+                concatenate(&mut string, next);
+                tokens.next();
+            }
+
+            Some(ASTNode::Value(ASTValue::StringValue(string)))
         },
         Token::StringLiteral(string) => {
-            if **tokens.peek().unwrap() == Token::Punctuation('=') && !as_value {
-                return _try_set_value(&ASTValue::StringLiteral(string.clone()), token, tokens)
-            }
             if **tokens.peek().unwrap() == Token::Punctuation('(') {
                 assert_eq!(*tokens.next().unwrap(), Token::Punctuation('('));
 
-                let mut parameters = vec![];
-                while **tokens.peek().expect("UNEXPECTED EOF") != Token::Punctuation(')') {
-                    if **tokens.peek().unwrap() == Token::Punctuation(',') {
-                        tokens.next();
-                    }
-                    let value = _parse(tokens.next().unwrap(), tokens, true);
-                    parameters.push(value.unwrap());
-                }
-
-                assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
-                if as_value {
-                    return Some(ASTNode::Value(ASTValue::FunctionCall(
-                        string.clone(),
-                        parameters,
-                    )));
-                }
-                assert_eq!(*tokens.next().unwrap(), Token::Punctuation(';'));
-                return Some(ASTNode::FunctionCall(string.clone(), parameters));
+                let parameters = parse_arguments(tokens);
+                return Some(ASTNode::Value(ASTValue::FunctionCall(
+                    string.clone(),
+                    parameters,
+                )));
             }
 
-            Some(ASTNode::Value(ASTValue::StringLiteral(string.clone())))
+            Some(parse_postfix(ASTNode::Value(ASTValue::StringLiteral(string.clone())), tokens))
         }
-        Token::Int(value) => Some(ASTNode::Value(ASTValue::IntValue(*value))),
+        Token::Int(value, suffix) => Some(int_literal(*value, *suffix)),
+        Token::Float(value, false) => Some(ASTNode::Value(ASTValue::FloatValue(*value))),
+        Token::Float(value, true) => Some(ASTNode::Cast(Type::FLOAT, Box::new(ASTNode::Value(ASTValue::FloatValue(*value))))),
         Token::Keyword(keyword) => match keyword.as_str() {
-            "int" | "void" | "char" => {
-                // Assume its going to be a function declaration for the time being
-                if as_value {
-                    panic!()
-                }
+            "true" => Some(ASTNode::Value(ASTValue::IntValue(1))),
+            "false" => Some(ASTNode::Value(ASTValue::IntValue(0))),
+            "sizeof" | "_Alignof" | "alignof" => {
+                let is_type_name = **tokens.peek().unwrap() == Token::Punctuation('(') && {
+                    let mut lookahead = tokens.clone();
+                    lookahead.next();
+                    lookahead.peek().is_some_and(|tk| Type::is_type_specifier(tk))
+                };
 
-                let ty = Type::read_type(token, tokens);
+                if is_type_name {
+                    tokens.next();
+                    let ty = Type::read_type(tokens.next().unwrap(), tokens);
+                    assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
 
-                let name = tokens.next().unwrap().extract_string_literal().unwrap();
+                    return if keyword == "sizeof" {
+                        Some(ASTNode::SizeOfType(ty))
+                    } else {
+                        Some(ASTNode::AlignOfType(ty))
+                    };
+                }
 
-                let function_or_variable = tokens.next().unwrap();
-                if Token::Punctuation('=') == *function_or_variable {
-                    // Variable Declaration
-                    let value = _parse(tokens.next().unwrap(), tokens, true).unwrap();
+                if keyword != "sizeof" {
+                    eprintln!("Error: {keyword} expects a type name");
+                    panic!()
+                }
 
-                    assert_eq!(*tokens.next().unwrap(), Token::Punctuation(';'));
-                    Some(ASTNode::VariableDeclaration(
-                        ty,
-                        name.clone(),
-                        Box::new(value),
-                    ))
-                } else if Token::Punctuation('(') == *function_or_variable {
-                    // Function Declaration
+                let operand = _parse(tokens.next().unwrap(), tokens).unwrap();
+                Some(ASTNode::SizeOf(Box::new(operand)))
+            }
+            "asm" | "__asm__" => {
+                // Every asm is treated as volatile, as nothing is ever optimised away
+                if matches!(tokens.peek(), Some(Token::Keyword(qualifier)) if qualifier == "volatile" || qualifier == "__volatile__")
+                {
+                    tokens.next();
+                }
+                assert_eq!(*tokens.next().unwrap(), Token::Punctuation('('));
 
-                    // Paramters
-                    let mut parameters = vec![];
-                    while **tokens.peek().expect("UNEXPECTED EOF") != Token::Punctuation(')') {
-                        let parameter_type = Type::read_type(tokens.next().unwrap(), tokens);
+                // Adjacent strings are joined, the text is kept exactly as written
+                let mut template = String::new();
+                while let Some(Token::StringValue(text)) = tokens.peek()
+                {
+                    concatenate(&mut template, text);
+                    tokens.next();
+                }
 
-                        let parameter_name = tokens.next().unwrap().extract_string_literal().unwrap();
+                // Outputs, inputs and then clobbers, each section starting with a :
+                let mut outputs = vec![];
+                let mut inputs = vec![];
+                let mut clobbers = vec![];
+                for mut operands in [Some(&mut outputs), Some(&mut inputs), None]
+                {
+                    if **tokens.peek().unwrap() != Token::Punctuation(':')
+                    {
+                        break;
+                    }
+                    tokens.next();
 
-                        parameters.push((parameter_name, parameter_type));
+                    while let Some(Token::StringValue(constraint)) = tokens.peek()
+                    {
+                        tokens.next();
+                        match operands.as_deref_mut()
+                        {
+                            Some(operands) => {
+                                assert_eq!(*tokens.next().unwrap(), Token::Punctuation('('));
+                                let value = parse_expression(tokens.next().unwrap(), tokens);
+                                assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
+                                operands.push((constraint.clone(), value));
+                            }
+                            None => clobbers.push(constraint.clone()),
+                        }
 
                         if **tokens.peek().unwrap() != Token::Punctuation(',')
                         {
@@ -217,34 +1077,43 @@ fn _parse(token: &Token, tokens: &mut Peekable<Iter<Token>>, as_value: bool) ->
                         }
                         tokens.next();
                     }
+                }
 
-                    assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
-                    assert_eq!(*tokens.next().unwrap(), Token::Punctuation('{'));
-
-                    let mut internal_nodes = vec![];
+                assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
+                assert_eq!(*tokens.next().unwrap(), Token::Punctuation(';'));
 
-                    while let Some(tk) = tokens.next() {
-                        if *tk == Token::Punctuation('}') {
-                            break;
-                        }
+                Some(ASTNode::ExtendedAssembly { template, outputs, inputs, clobbers })
+            }
+            "va_start" | "va_arg" | "va_end" => {
+                assert_eq!(*tokens.next().unwrap(), Token::Punctuation('('));
+                let list = Box::new(parse_expression(tokens.next().unwrap(), tokens));
 
-                        internal_nodes.push(_parse(tk, tokens, false).unwrap())
-                    }
+                if keyword == "va_end" {
+                    assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
+                    return Some(ASTNode::VaEnd(list));
+                }
 
-                    Some(ASTNode::FunctionDeclaration(
-                        ty,
-                        name.clone(),
-                        internal_nodes,
-                        parameters,
-                    ))
+                assert_eq!(*tokens.next().unwrap(), Token::Punctuation(','));
+                let node = if keyword == "va_arg" {
+                    ASTNode::VaArg(list, Type::read_type(tokens.next().unwrap(), tokens))
                 } else {
-                    None
-                }
+                    // The last named parameter isn't needed, the function's own type says where
+                    // the variable arguments start
+                    parse_expression(tokens.next().unwrap(), tokens);
+                    ASTNode::VaStart(list)
+                };
+                assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
+                Some(node)
+            }
+            // Declarations in statement position are parsed by parse_declaration
+            "static" | "extern" | "const" | "volatile" | "va_list" | "int" | "void" | "_Bool" | "bool" | "char" | "short" | "long" | "signed" | "unsigned" | "float" | "double" => {
+                eprintln!("Error: Expected an expression, got the declaration starting with {keyword}");
+                panic!()
             },
             "if" => {
                 assert_eq!(*tokens.next().unwrap(), Token::Punctuation('('));
 
-                let predicate = parse_comparison(tokens.next().unwrap(), tokens).unwrap();
+                let predicate = Box::new(parse_comma_expression(tokens.next().unwrap(), tokens));
 
                 assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
                 assert_eq!(*tokens.next().unwrap(), Token::Punctuation('{'));
@@ -256,7 +1125,8 @@ fn _parse(token: &Token, tokens: &mut Peekable<Iter<Token>>, as_value: bool) ->
                         break;
                     }
 
-                    main_body.push(_parse(tk, tokens, false).unwrap());
+                    main_body.extend(source_line(tokens));
+                    main_body.extend(parse_statement(tk, tokens));
                 }
 
                 Some(ASTNode::If { predicate , main_body, else_body: None })
@@ -267,7 +1137,7 @@ fn _parse(token: &Token, tokens: &mut Peekable<Iter<Token>>, as_value: bool) ->
                     return Some(ASTNode::Return(None));
                 }
 
-                let value = _parse(
+                let value = parse_comma_expression(
                     if let Some(val) = tokens.next() {
                         val
                     } else {
@@ -275,9 +1145,7 @@ fn _parse(token: &Token, tokens: &mut Peekable<Iter<Token>>, as_value: bool) ->
                         panic!()
                     },
                     tokens,
-                    true,
-                )
-                .unwrap();
+                );
 
                 assert_eq!(*tokens.next().unwrap(), Token::Punctuation(';'));
 
@@ -289,21 +1157,32 @@ fn _parse(token: &Token, tokens: &mut Peekable<Iter<Token>>, as_value: bool) ->
             }
         },
         Token::Punctuation(punc) => match *punc {
-            '*' => {
-                let val = ASTValue::Deref(
-                    tokens
-                        .next()
-                        .unwrap()
-                        .extract_string_literal()
-                        .expect("Expected String Literal"),
-                );
+            '(' if Type::is_type_specifier(tokens.peek().unwrap()) => {
+                let ty = Type::read_type(tokens.next().unwrap(), tokens);
+                assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
 
-                if let Some(set_value) = _try_set_value(&val, token, tokens)
-                {
-                    return Some(set_value);
-                } else
-                {
-                    return Some(ASTNode::Value(val))
+                let value = _parse(tokens.next().unwrap(), tokens).unwrap();
+                Some(ASTNode::Cast(ty, Box::new(value)))
+            }
+            '(' => {
+                let inner = parse_comma_expression(tokens.next().unwrap(), tokens);
+                assert_eq!(*tokens.next().unwrap(), Token::Punctuation(')'));
+
+                if **tokens.peek().unwrap() != Token::Punctuation('(') {
+                    return Some(parse_postfix(inner, tokens));
+                }
+                tokens.next();
+
+                let parameters = parse_arguments(tokens);
+                Some(ASTNode::Value(ASTValue::IndirectCall(
+                    Box::new(inner),
+                    parameters,
+                )))
+            }
+            '*' => {
+                match _parse(tokens.next().unwrap(), tokens).unwrap() {
+                    ASTNode::Value(ASTValue::StringLiteral(name)) => Some(ASTNode::Value(ASTValue::Deref(name))),
+                    pointer => Some(ASTNode::Deref(Box::new(pointer))),
                 }
             },
             '&' => Some(ASTNode::Value(ASTValue::Ref(
@@ -323,10 +1202,11 @@ fn _parse(token: &Token, tokens: &mut Peekable<Iter<Token>>, as_value: bool) ->
                         let curr = tokens.next().unwrap();
                         let curr = match curr {
                             Token::StringLiteral(a) | Token::Keyword(a) | Token::StringValue(a) | Token::MathSymbol(a)  => a.clone(),
-                            Token::Int(a) => a.to_string(),
-                            Token::Float(a) => a.to_string(),
+                            Token::Int(a, _) => a.to_string(),
+                            Token::Float(a, _) => a.to_string(),
                             Token::Punctuation(a) => a.to_string(),
                             Token::CharValue(a) => a.to_string(),
+                            Token::Ellipsis => "...".to_string(),
                         };
                         buffer.push_str(&curr);
                         buffer.push(' ');
@@ -350,30 +1230,65 @@ fn _parse(token: &Token, tokens: &mut Peekable<Iter<Token>>, as_value: bool) ->
                 None
             }
         },
-        Token::MathSymbol(x) if matches!(x.as_str(), "+" | "-") => {
-            let lhs = _parse(tokens.next().unwrap(), tokens, true).unwrap();
-            let rhs = _parse(tokens.next().unwrap(), tokens, true).unwrap();
+        Token::MathSymbol(x) if x == "-" => {
+            // Unary minus, folded straight into integer literals
+            let operand = _parse(tokens.next().unwrap(), tokens).unwrap();
 
-            if x == "+"
+            if let ASTNode::Value(ASTValue::IntValue(value)) = operand
             {
-                Some(ASTNode::Add(Box::new(lhs), Box::new(rhs)))
+                Some(ASTNode::Value(ASTValue::IntValue(-value)))
+            } else if let ASTNode::Value(ASTValue::FloatValue(value)) = operand
+            {
+                Some(ASTNode::Value(ASTValue::FloatValue(-value)))
             } else
             {
-                Some(ASTNode::Sub(Box::new(lhs), Box::new(rhs)))
+                Some(ASTNode::Sub(Box::new(ASTNode::Value(ASTValue::IntValue(0))), Box::new(operand)))
             }
         }
-        Token::MathSymbol(_) | Token::Float(_) => panic!(),
+        Token::MathSymbol(x) if x == "+" => _parse(tokens.next().unwrap(), tokens),
+        Token::MathSymbol(x) if x == "!" => {
+            let operand = _parse(tokens.next().unwrap(), tokens).unwrap();
+            Some(ASTNode::Not(Box::new(operand)))
+        }
+        Token::MathSymbol(x) if x == "++" || x == "--" => {
+            let operand = _parse(tokens.next().unwrap(), tokens).unwrap();
+            Some(ASTNode::Increment { lvalue: Box::new(operand), decrement: x == "--", postfix: false })
+        }
+        Token::MathSymbol(x) if x == "~" => {
+            let operand = _parse(tokens.next().unwrap(), tokens).unwrap();
+            Some(ASTNode::BitNot(Box::new(operand)))
+        }
+        Token::MathSymbol(_) => panic!(),
+        Token::Ellipsis => {
+            eprintln!("Error: Unexpected ... outside of a parameter list");
+            panic!()
+        }
     }
 }
 
-pub fn parse(tokens: Vec<Token>) -> Vec<ASTNode> {
-    let mut nodes = vec![];
+/// A `SourceLine` for the token just taken from `tokens`, if locations are known
+fn source_line(tokens: &Tokens) -> Option<ASTNode> {
+    let location = tokens.location()?;
+    Some(ASTNode::SourceLine(location.file.clone(), location.line))
+}
 
-    let mut tokens = tokens.iter().peekable();
+fn parse_tokens(mut tokens: Tokens) -> Vec<ASTNode> {
+    let mut nodes = vec![];
 
     while let Some(token) = tokens.next() {
-        nodes.push(_parse(token, &mut tokens, false).unwrap())
+        nodes.extend(source_line(&tokens));
+        nodes.extend(parse_statement(token, &mut tokens))
     }
 
     nodes
 }
+
+pub fn parse(tokens: Vec<Token>) -> Vec<ASTNode> {
+    parse_tokens(Tokens::new(&tokens, &[]))
+}
+
+/// Parses, marking each statement with where it starts, `locations` being one per token
+pub fn parse_with_locations(tokens: Vec<Token>, locations: Vec<Location>) -> Vec<ASTNode> {
+    assert_eq!(tokens.len(), locations.len());
+    parse_tokens(Tokens::new(&tokens, &locations))
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::tokenise::{tokenise, tokenise_with_lines, Location, Token};
+
+/// Headers shipped with the compiler, found by `#include <name>`
+const HEADERS: [(&str, &str); 10] = [
+    ("errno.h", include_str!("include/errno.h")),
+    ("fcntl.h", include_str!("include/fcntl.h")),
+    ("stdarg.h", include_str!("include/stdarg.h")),
+    ("stdbool.h", include_str!("include/stdbool.h")),
+    ("stddef.h", include_str!("include/stddef.h")),
+    ("stdint.h", include_str!("include/stdint.h")),
+    ("stdio.h", include_str!("include/stdio.h")),
+    ("stdlib.h", include_str!("include/stdlib.h")),
+    ("string.h", include_str!("include/string.h")),
+    ("unistd.h", include_str!("include/unistd.h")),
+];
+
+/// The bundled headers that don't need the prelude's C library, the rest are refused under
+/// `-ffreestanding` and `-nostdlib` as nothing implements what they declare
+const FREESTANDING_HEADERS: [&str; 4] = ["stdarg.h", "stdbool.h", "stddef.h", "stdint.h"];
+
+/// Handles `#include` and object-like `#define`, the only directives supported
+#[derive(Debug, Default)]
+pub struct Preprocessor {
+    defines: HashMap<String, Vec<Token>>,
+    /// Every header is included at most once, as if it had an include guard
+    included: HashSet<String>,
+    /// The lines of every file read, by the name used in its locations
+    pub sources: HashMap<String, Vec<String>>,
+    /// Only the freestanding headers can be included
+    freestanding: bool,
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A preprocessor for programs built without the prelude's C library
+    pub fn freestanding() -> Self {
+        Self { freestanding: true, ..Self::default() }
+    }
+
+    /// Tokenises a file named `file`, `directory` being where `#include "name"` looks for files.
+    /// Also gives where each token came from, a macro's replacement being where it was used
+    pub fn preprocess(&mut self, contents: &str, file: &str, directory: Option<&Path>) -> (Vec<Token>, Vec<Location>) {
+        let mut tokens = vec![];
+        let mut locations = vec![];
+        let mut buffer = String::new();
+        // The line the buffer starts on
+        let mut buffer_line = 1;
+
+        self.sources.insert(file.to_string(), contents.lines().map(String::from).collect());
+
+        for (index, line) in contents.lines().enumerate() {
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                buffer.push_str(line);
+                buffer.push('\n');
+                continue;
+            };
+
+            // The lines so far are tokenised first, so defines only apply after themselves
+            self.append_buffer(&buffer, file, buffer_line, &mut tokens, &mut locations);
+            buffer.clear();
+            // Skips the directive, its line being counted from 1
+            buffer_line = index as u32 + 2;
+
+            let directive = directive.trim();
+            let (name, rest) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+            match name {
+                "include" => {
+                    let (mut included, mut included_locations) = self.include(rest.trim(), directory);
+                    tokens.append(&mut included);
+                    locations.append(&mut included_locations);
+                }
+                "define" => {
+                    let rest = rest.trim();
+                    let (name, replacement) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                    self.defines.insert(name.to_string(), tokenise(format!("{replacement}\n")));
+                }
+                _ => {
+                    eprintln!("Error: Unsupported preprocessor directive #{directive}");
+                    panic!()
+                }
+            }
+        }
+
+        self.append_buffer(&buffer, file, buffer_line, &mut tokens, &mut locations);
+        (tokens, locations)
+    }
+
+    /// Tokenises and expands lines without directives, the first being line `first_line` of `file`
+    fn append_buffer(&self, buffer: &str, file: &str, first_line: u32, tokens: &mut Vec<Token>, locations: &mut Vec<Location>) {
+        let (buffer_tokens, lines) = tokenise_with_lines(buffer);
+
+        for (token, line) in buffer_tokens.into_iter().zip(lines) {
+            let expanded = self.expand(vec![token], &mut vec![]);
+            let location = Location { file: file.to_string(), line: first_line + line - 1 };
+            locations.resize(locations.len() + expanded.len(), location);
+            tokens.extend(expanded);
+        }
+    }
+
+    fn include(&mut self, target: &str, directory: Option<&Path>) -> (Vec<Token>, Vec<Location>) {
+        let (key, contents, directory) = if let Some(name) = target.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+            let Some((_, contents)) = HEADERS.iter().find(|(header, _)| *header == name) else {
+                eprintln!("Error: No bundled header named {name}");
+                panic!()
+            };
+            if self.freestanding && !FREESTANDING_HEADERS.contains(&name) {
+                eprintln!("Error: <{name}> needs the C library, it can't be included with -ffreestanding or -nostdlib");
+                panic!()
+            }
+            (format!("<{name}>"), contents.to_string(), None)
+        } else if let Some(name) = target.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+            let path = directory.unwrap_or(Path::new(".")).join(name);
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    eprintln!("Error: Could not include {}: {error}", path.display());
+                    panic!()
+                }
+            };
+            let directory = path.parent().map(PathBuf::from);
+            (path.display().to_string(), contents, directory)
+        } else {
+            eprintln!("Error: Expected <name> or \"name\" after #include, got {target}");
+            panic!()
+        };
+
+        if !self.included.insert(key.clone()) {
+            return (vec![], vec![]);
+        }
+
+        self.preprocess(&contents, &key, directory.as_deref())
+    }
+
+    /// Replaces every defined name, a define is never expanded inside its own replacement
+    fn expand(&self, tokens: Vec<Token>, expanding: &mut Vec<String>) -> Vec<Token> {
+        let mut expanded = vec![];
+
+        for token in tokens {
+            match &token {
+                Token::StringLiteral(name) if self.defines.contains_key(name) && !expanding.contains(name) => {
+                    expanding.push(name.clone());
+                    expanded.append(&mut self.expand(self.defines[name].clone(), expanding));
+                    expanding.pop();
+                }
+                _ => expanded.push(token),
+            }
+        }
+
+        expanded
+    }
+}
@@ -1,9 +1,10 @@
-use std::{fmt::{format, Display}, iter::Peekable, str::Chars};
+use std::{cell::Cell, fmt::{format, Display}, iter::Peekable, str::Chars};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
-    Int(i32),
-    Float(f32),
+    Int(u64, IntSuffix),
+    /// The value and whether it has an `f` suffix, making it a `float` rather than a `double`
+    Float(f64, bool),
     CharValue(char),
     StringValue(String),
     
@@ -11,6 +12,44 @@ pub enum Token {
     Keyword(String),
     Punctuation(char),
     MathSymbol(String),
+    /// `...` ending a variadic parameter list
+    Ellipsis,
+}
+
+/// The `u` and `l` suffixes of an integer literal, `ll` counts as `l` as both are 64 bits
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct IntSuffix {
+    pub unsigned: bool,
+    pub long: bool,
+}
+
+impl IntSuffix {
+    fn parse(suffix: &str) -> Option<Self> {
+        let (unsigned, rest) = match suffix.strip_prefix(['u', 'U']) {
+            Some(rest) => (true, rest),
+            None => (false, suffix),
+        };
+        let (long, rest) = match rest {
+            _ if rest.starts_with("ll") || rest.starts_with("LL") => (true, &rest[2..]),
+            _ if rest.starts_with(['l', 'L']) => (true, &rest[1..]),
+            _ => (false, rest),
+        };
+        // The u may also come last, as in `1lu`
+        let unsigned = match rest {
+            "" => unsigned,
+            "u" | "U" if !unsigned => true,
+            _ => return None,
+        };
+
+        Some(Self { unsigned, long })
+    }
+}
+
+/// Where a token starts in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub file: String,
+    pub line: u32,
 }
 
 impl Display for Token
@@ -30,50 +69,129 @@ impl Token
             _ => None
         }
     }
+
+    pub fn extract_keyword(&self) -> Option<String>
+    {
+        match self
+        {
+            Self::Keyword(a) => Some(a.clone()),
+            _ => None
+        }
+    }
+}
+
+/// The character a backslash escape stands for, reading what follows the backslash. Octal
+/// escapes take up to three digits and hexadecimal ones every hex digit after the `x`, both
+/// giving the byte with that value
+pub fn escaped_char<I: Iterator<Item = char>>(chars: &mut Peekable<I>) -> char {
+    let value = match chars.next() {
+        Some('n') => return '\n',
+        Some('t') => return '\t',
+        Some('r') => return '\r',
+        Some('a') => return '\u{7}',
+        Some('b') => return '\u{8}',
+        Some('f') => return '\u{c}',
+        Some('v') => return '\u{b}',
+        Some(c @ ('\\' | '\'' | '"' | '?')) => return c,
+        Some(c @ '0'..='7') => {
+            let mut value = c.to_digit(8).unwrap();
+            for _ in 0..2 {
+                match chars.next_if(|c| c.is_digit(8)) {
+                    Some(digit) => value = value * 8 + digit.to_digit(8).unwrap(),
+                    None => break,
+                }
+            }
+            value
+        }
+        Some('x') => {
+            let mut digits = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_hexdigit) {
+                digits.push(digit);
+            }
+            if digits.is_empty() {
+                eprintln!("Error: \\x used with no following hex digits");
+                panic!()
+            }
+            u32::from_str_radix(&digits, 16).unwrap_or(u32::MAX)
+        }
+        c => {
+            eprintln!("Error: Unsupported escape sequence \\{}", c.unwrap_or(' '));
+            panic!()
+        }
+    };
+
+    match u8::try_from(value) {
+        Ok(byte) => char::from(byte),
+        Err(_) => {
+            eprintln!("Error: Escape sequence out of range, its value doesn't fit in a char");
+            panic!()
+        }
+    }
 }
 
 pub fn is_keyword(s: &str) -> bool {
-    ["int", "void", "char", "return", "if"].contains(&s)
+    [
+        "int", "void", "char", "short", "long", "signed", "unsigned", "float", "double", "_Bool",
+        "bool", "true", "false", "sizeof", "_Alignof", "alignof", "static", "extern", "const", "volatile",
+        "va_list", "va_start", "va_arg", "va_end", "asm", "__asm__", "__volatile__", "return", "if",
+    ]
+    .contains(&s)
 }
 
 pub fn is_str_literal_char(c: char) -> bool {
-    "qwertyuiopasdfghjklzxcvbnm_".contains(c)
+    c.is_ascii_alphabetic() || c == '_'
 }
 
 pub fn is_punc_char(c: char) -> bool {
     // TODO: Detect if * is being used as a dereference or a multiply
-    "();,[]{}=&*".contains(c)
+    "();,[]{}=&*?:".contains(c)
 }
 
 pub fn is_math_char(c: char) -> bool {
-    "+-<>".contains(c)
+    "+-<>%|^~".contains(c)
 }
 
-/// TODO: structure better
 pub fn tokenise<S>(contents: S) -> Vec<Token>
+where
+    S: AsRef<str>,
+{
+    tokenise_with_lines(contents).0
+}
+
+/// Tokenises, also giving the line each token starts on, counting from 1
+/// TODO: structure better
+pub fn tokenise_with_lines<S>(contents: S) -> (Vec<Token>, Vec<u32>)
 where
     S: AsRef<str>,
 {
     let mut tokens = vec![];
+    let mut lines = vec![];
 
     let contents = contents.as_ref().to_string();
 
-    let mut iter = contents.chars().peekable();
+    // Counts the newlines read so far, peeking only ever reads the character being looked at
+    let line = Cell::new(1);
+    let mut iter = contents.chars().inspect(|c| if *c == '\n' { line.set(line.get() + 1) }).peekable();
 
     let mut buffer = String::new();
 
-    let mut is_negative = false;
-
-    'token_loop:
     while let Some(c) = iter.next() {
+        let start_line = line.get();
+
         if is_punc_char(c) {
-            // extra checks for ==, treat is as math symbol
-            if iter.peek().is_some() && *iter.peek().unwrap() == '=' 
+            // extra checks for ==, &&, *= and &=, treat them as math symbols
+            if c == '=' && iter.peek().is_some() && *iter.peek().unwrap() == '='
             {
                 iter.next();
                 tokens.push(Token::MathSymbol("==".to_string()));
-                let len = tokens.len();
-                tokens.swap(len - 2, len - 1);
+            } else if c == '&' && iter.peek().is_some() && *iter.peek().unwrap() == '&'
+            {
+                iter.next();
+                tokens.push(Token::MathSymbol("&&".to_string()));
+            } else if ['*', '&'].contains(&c) && iter.peek().is_some() && *iter.peek().unwrap() == '='
+            {
+                iter.next();
+                tokens.push(Token::MathSymbol(format!("{c}=")));
             } else
             {
                 tokens.push(Token::Punctuation(c))
@@ -83,7 +201,7 @@ where
             buffer.push(c);
 
             while let Some(c2) = iter.peek() {
-                if !is_str_literal_char(*c2) {
+                if !(is_str_literal_char(*c2) || c2.is_ascii_digit()) {
                     break;
                 }
                 buffer.push(iter.next().unwrap())
@@ -97,11 +215,6 @@ where
 
             buffer.clear();
         } else if c.is_ascii_digit() {
-            if is_negative
-            {
-                buffer.push('-');
-                is_negative = false;
-            }
             buffer.push(c);
 
             while let Some(c2) = iter.peek() {
@@ -111,59 +224,116 @@ where
                 buffer.push(iter.next().unwrap())
             }
 
+            // An exponent, as in 1e3 or 2.5E-4
+            let has_exponent = matches!(iter.peek(), Some('e' | 'E'));
+            if has_exponent {
+                buffer.push(iter.next().unwrap());
+                if let Some(sign) = iter.next_if(|c2| *c2 == '+' || *c2 == '-') {
+                    buffer.push(sign);
+                }
+                while let Some(digit) = iter.next_if(char::is_ascii_digit) {
+                    buffer.push(digit);
+                }
+            }
+
+            let mut suffix = String::new();
+            while let Some(c2) = iter.peek().filter(|c2| c2.is_ascii_alphanumeric()) {
+                suffix.push(*c2);
+                iter.next();
+            }
+
             let dot_count = buffer.matches(".").collect::<Vec<&str>>().len();
-            if dot_count == 1 {
-                tokens.push(Token::Float(
-                    buffer
-                        .parse::<f32>()
-                        .expect(&format!("Error: Could not parse {} as a float", buffer)),
-                ))
+            if dot_count == 1 || (dot_count == 0 && has_exponent) {
+                let is_float = match suffix.as_str() {
+                    "" => false,
+                    "f" | "F" => true,
+                    _ => {
+                        eprintln!("Error: Invalid suffix {suffix} on floating point literal {buffer}");
+                        panic!()
+                    }
+                };
+                let Ok(value) = buffer.parse::<f64>() else {
+                    eprintln!("Error: Could not parse {buffer} as a float");
+                    panic!()
+                };
+                tokens.push(Token::Float(value, is_float))
             } else if dot_count > 1 {
                 // Not a valid number
                 eprintln!("Error: {} is not a valid number", buffer);
-                return vec![];
+                return (vec![], vec![]);
             } else {
-                tokens.push(Token::Int(
-                    buffer
-                        .parse::<i32>()
-                        .expect(&format!("Error: Could not parse {} as an int", buffer)),
-                ))
+                let Some(suffix) = IntSuffix::parse(&suffix) else {
+                    eprintln!("Error: Invalid suffix {suffix} on integer literal {buffer}");
+                    panic!()
+                };
+                let Ok(value) = buffer.parse::<u64>() else {
+                    eprintln!("Error: Integer literal {buffer} is too large");
+                    panic!()
+                };
+                tokens.push(Token::Int(value, suffix))
             }
 
             buffer.clear();
         } else if is_math_char(c) {
-            // But, make an exception for numbers
-            if iter.peek().unwrap().is_ascii_digit()
+            let mut symbol = c.to_string();
+
+            // <<, >>, ||, ++ and --
+            if ['<', '>', '|', '+', '-'].contains(&c) && iter.peek() == Some(&c)
             {
-                is_negative = true;
-                continue 'token_loop;
+                symbol.push(iter.next().unwrap());
             }
 
-            if ['>', '<'].contains(&c) && iter.peek().is_some() && *iter.peek().unwrap() == '='
+            // Comparisons and compound assignments, such as <= and <<=
+            if !["||", "++", "--", "~"].contains(&symbol.as_str()) && iter.peek() == Some(&'=')
+            {
+                symbol.push(iter.next().unwrap());
+            }
+
+            tokens.push(Token::MathSymbol(symbol));
+        } else if c == '!'
+        {
+            if iter.peek().is_some() && *iter.peek().unwrap() == '='
             {
                 iter.next();
-                tokens.push(
-                    Token::MathSymbol(format!("{c}="))
-                );
+                tokens.push(Token::MathSymbol("!=".to_string()));
             } else
             {
-                tokens.push(
-                    Token::MathSymbol(c.to_string())
-                );
+                tokens.push(Token::MathSymbol("!".to_string()));
             }
-
-            let len = tokens.len();
-            tokens.swap(len - 2, len - 1);
+        } else if c == '.'
+        {
+            if iter.next() != Some('.') || iter.next() != Some('.')
+            {
+                eprintln!("Error: Expected ...");
+                panic!()
+            }
+            tokens.push(Token::Ellipsis);
         } else if c.is_whitespace() {
             // recognise it but dont do anything
         } else if c == '\''
         {
-            tokens.push(Token::CharValue(iter.next().unwrap()));
-            assert!(iter.next().unwrap() == '\'');
+            let value = match iter.next() {
+                Some('\\') => escaped_char(&mut iter),
+                Some(value) => value,
+                None => {
+                    eprintln!("Error: Unterminated character literal");
+                    panic!()
+                }
+            };
+            if iter.next() != Some('\'') {
+                eprintln!("Error: Expected ' to end the character literal '{}'", value.escape_default());
+                panic!()
+            }
+            tokens.push(Token::CharValue(value));
         } else if c == '\"'
         {
             while let Some(c2) = iter.peek() {
                 if *c2 == '\"' {break;}
+                // Escape sequences are kept as written, so `\"` doesn't end the string
+                if *c2 == '\\'
+                {
+                    buffer.push(iter.next().unwrap());
+                }
                 buffer.push(iter.next().unwrap())
             }
             assert!(iter.next().unwrap() == '\"');
@@ -178,16 +348,21 @@ where
                 {
 
                 }                
+            } else if iter.peek().is_some() && *iter.peek().unwrap() == '='
+            {
+                iter.next();
+                tokens.push(Token::MathSymbol("/=".to_string()));
             } else
             {
-                eprintln!("Unexpected /");
-                panic!()
+                tokens.push(Token::MathSymbol(c.to_string()));
             }
         } else {
             eprintln!("Error: Unrecognised character: {}", c);
             panic!()
         }
+
+        lines.resize(tokens.len(), start_line);
     }
 
-    tokens
+    (tokens, lines)
 }
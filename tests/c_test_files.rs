@@ -0,0 +1,90 @@
+//! Compiles every program in `c_test_files` and runs it, checking its exit status and output
+//! against the leading comment of the file:
+//! - `// args: <flags>`, passed to the compiler before the file
+//! - `// exit: <status>`, which every file has
+//! - `// stdout: <line>`, one for each line printed
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+struct Expected {
+    args: Vec<String>,
+    exit: i32,
+    stdout: String,
+}
+
+fn expected(source: &str) -> Result<Expected, String> {
+    let mut args = vec![];
+    let mut exit = None;
+    let mut stdout = String::new();
+
+    for line in source.lines().take_while(|line| line.starts_with("//")) {
+        if let Some(flags) = line.strip_prefix("// args:") {
+            args.extend(flags.split_whitespace().map(String::from));
+        } else if let Some(status) = line.strip_prefix("// exit:") {
+            exit = Some(status.trim().parse().map_err(|_| format!("invalid exit status {status}"))?);
+        } else if let Some(output) = line.strip_prefix("// stdout:") {
+            stdout.push_str(output.strip_prefix(' ').unwrap_or(output));
+            stdout.push('\n');
+        }
+    }
+
+    let exit = exit.ok_or("no // exit: line")?;
+    Ok(Expected { args, exit, stdout })
+}
+
+/// Builds `file` in a directory of its own, as the compiler writes `test.asm` and `test` to the
+/// working directory, then runs it with no input
+fn run(file: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(file).map_err(|error| error.to_string())?;
+    let expected = expected(&source)?;
+
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join(file.file_stem().unwrap());
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).map_err(|error| error.to_string())?;
+
+    let compile = Command::new(env!("CARGO_BIN_EXE_c_compiler"))
+        .args(&expected.args)
+        .arg(file)
+        .current_dir(&directory)
+        .output()
+        .map_err(|error| error.to_string())?;
+    if !compile.status.success() {
+        return Err(format!("didn't compile\n{}", String::from_utf8_lossy(&compile.stderr)));
+    }
+
+    let output = Command::new("./test")
+        .current_dir(&directory)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|error| format!("couldn't run the output: {error}"))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout != expected.stdout {
+        return Err(format!("printed {stdout:?}, expected {:?}", expected.stdout));
+    }
+    match output.status.code() {
+        Some(code) if code == expected.exit => Ok(()),
+        code => Err(format!("exited with {code:?}, expected {}", expected.exit)),
+    }
+}
+
+#[test]
+fn c_test_files() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("c_test_files");
+    let mut files = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "c"))
+        .collect::<Vec<PathBuf>>();
+    files.sort();
+
+    let failures = files
+        .iter()
+        .filter_map(|file| run(file).err().map(|error| format!("{}: {error}", file.display())))
+        .collect::<Vec<String>>();
+    assert!(failures.is_empty(), "{} of {} failed\n{}", failures.len(), files.len(), failures.join("\n"));
+}